@@ -0,0 +1,182 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+
+const ADTS_HEADER_LENGTH: usize = 7;
+const ADTS_CRC_LENGTH: usize = 2;
+const SAMPLES_PER_RAW_DATA_BLOCK: u64 = 1024;
+
+// sync followed by every MPEG version with and without crc, layer is always 0
+const ADTS_IDENTIFIERS: [&[u8]; 4] = [
+    &[0xFF, 0xF0], &[0xFF, 0xF1], &[0xFF, 0xF8], &[0xFF, 0xF9],
+];
+
+const ADTS_SAMPLING_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+const ADTS_PROFILES: [&str; 4] = ["Main", "LC", "SSR", "LTP"];
+
+// Fixed part of an ADTS frame header that stays the same throughout a stream
+// and the length of a particular frame
+struct AdtsHeader {
+    mpeg_version: u8,
+    protection_absent: bool,
+    profile: u8,
+    sampling_rate_index: u8,
+    channel_configuration: u8,
+    frame_length: usize,
+    raw_data_blocks: u8,
+}
+
+impl AdtsHeader {
+    fn from_bytes(header_bytes: &[u8]) -> Option<AdtsHeader> {
+        if header_bytes[0] != 0xFF || header_bytes[1] & 0xF6 != 0xF0 {
+            return None;
+        }
+
+        let protection_absent: bool = header_bytes[1] & 0x01 != 0;
+        let sampling_rate_index: u8 = (header_bytes[2] >> 2) & 0x0F;
+        if sampling_rate_index as usize >= ADTS_SAMPLING_RATES.len() {
+            return None;
+        }
+
+        let frame_length: usize = (((header_bytes[3] & 0x03) as usize) << 11)
+            | ((header_bytes[4] as usize) << 3)
+            | ((header_bytes[5] as usize) >> 5);
+        let header_length: usize = if protection_absent {ADTS_HEADER_LENGTH} else {ADTS_HEADER_LENGTH + ADTS_CRC_LENGTH};
+        if frame_length <= header_length {
+            return None;
+        }
+
+        return Some(AdtsHeader{
+            mpeg_version: if header_bytes[1] & 0x08 != 0 {2} else {4},
+            protection_absent: protection_absent,
+            profile: header_bytes[2] >> 6,
+            sampling_rate_index: sampling_rate_index,
+            channel_configuration: ((header_bytes[2] & 0x01) << 2) | (header_bytes[3] >> 6),
+            frame_length: frame_length,
+            raw_data_blocks: (header_bytes[6] & 0x03) + 1,
+        });
+    }
+
+    // Whether other frame could belong to the same stream
+    fn is_compatible(&self, other: &AdtsHeader) -> bool {
+        return self.mpeg_version == other.mpeg_version
+            && self.protection_absent == other.protection_absent
+            && self.profile == other.profile
+            && self.sampling_rate_index == other.sampling_rate_index
+            && self.channel_configuration == other.channel_configuration;
+    }
+}
+
+// Tries to read an ADTS AAC stream that starts exactly at start_index by following
+// its chained frames the same way untagged mp3 streams are followed.
+// There must be at least min_frames frames to be sure it's not a coincidence
+pub fn rip_aac(data: &[u8], start_index: usize, min_frames: usize) -> Option<Position> {
+    if start_index + ADTS_HEADER_LENGTH > data.len() {
+        return None;
+    }
+
+    let first_header: AdtsHeader = AdtsHeader::from_bytes(&data[start_index..start_index + ADTS_HEADER_LENGTH])?;
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: start_index,
+        content_type: ContentType::AAC,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    let mut frame_count: usize = 0;
+    let mut sample_count: u64 = 0;
+    while position.end + ADTS_HEADER_LENGTH <= data.len() {
+        let header: AdtsHeader;
+        match AdtsHeader::from_bytes(&data[position.end..position.end + ADTS_HEADER_LENGTH]) {
+            Some(frame_header) if first_header.is_compatible(&frame_header) => {
+                header = frame_header;
+            }
+            _ => {
+                break;
+            }
+        }
+
+        position.end += header.frame_length;
+        frame_count += 1;
+        sample_count += header.raw_data_blocks as u64 * SAMPLES_PER_RAW_DATA_BLOCK;
+    }
+
+    if frame_count < min_frames {
+        return None;
+    }
+
+    // the last frame might not fit entirely
+    // or there's not enough data left to tell whether another frame follows
+    if position.end != data.len() && position.end + ADTS_HEADER_LENGTH > data.len() {
+        position.end = data.len();
+        position.validity = Validity::TRUNCATED;
+    }
+
+    let sampling_rate: u32 = ADTS_SAMPLING_RATES[first_header.sampling_rate_index as usize];
+    position.metadata.push(("version", format!("MPEG-{}", first_header.mpeg_version)));
+    position.metadata.push(("profile", String::from(ADTS_PROFILES[first_header.profile as usize])));
+    position.metadata.push(("sampling_rate", sampling_rate.to_string()));
+    position.metadata.push(("channels", first_header.channel_configuration.to_string()));
+    position.metadata.push(("frames", frame_count.to_string()));
+    position.metadata.push(("duration", format!("{:.3}", sample_count as f64 / sampling_rate as f64)));
+
+    return Some(position);
+}
+
+pub struct AacRipper {
+    // how many frames a stream must have
+    pub min_frames: usize,
+}
+
+impl Ripper for AacRipper {
+    fn name(&self) -> &'static str {
+        return "AAC";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::AAC;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::AUDIO;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &ADTS_IDENTIFIERS;
+    }
+
+    // every frame starts with a sync, no need to look at each of them again
+    fn can_nest(&self) -> bool {
+        return false;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_aac(data, start_index, self.min_frames);
+    }
+}
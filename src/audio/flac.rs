@@ -0,0 +1,384 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+
+const FLAC_IDENTIFIER: [u8; 4] = *b"fLaC";
+const METADATA_BLOCK_HEADER_LENGTH: usize = 4;
+const STREAMINFO_LENGTH: usize = 34;
+
+// metadata block types
+const STREAMINFO: u8 = 0;
+const PICTURE: u8 = 6;
+const LAST_BLOCK_FLAG: u8 = 0x80;
+
+// sync code, reserved bit and blocking strategy
+const FRAME_SYNC_MASK: u16 = 0xFFFE;
+const FRAME_SYNC: u16 = 0xFFF8;
+// room for frame and subframe headers, padding and the crc on top of samples
+const FRAME_OVERHEAD: usize = 64;
+
+// CRC-8 of frame headers (polynomial 0x07) and CRC-16 of whole frames (polynomial 0x8005)
+const CRC8_TABLE: [u8; 256] = make_crc8_table();
+const CRC16_TABLE: [u16; 256] = make_crc16_table();
+
+const fn make_crc8_table() -> [u8; 256] {
+    let mut table: [u8; 256] = [0; 256];
+    let mut n: usize = 0;
+    while n < 256 {
+        let mut c: u8 = n as u8;
+        let mut k: usize = 0;
+        while k < 8 {
+            if c & 0x80 != 0 {
+                c = (c << 1) ^ 0x07;
+            } else {
+                c = c << 1;
+            }
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    return table;
+}
+
+const fn make_crc16_table() -> [u16; 256] {
+    let mut table: [u16; 256] = [0; 256];
+    let mut n: usize = 0;
+    while n < 256 {
+        let mut c: u16 = (n as u16) << 8;
+        let mut k: usize = 0;
+        while k < 8 {
+            if c & 0x8000 != 0 {
+                c = (c << 1) ^ 0x8005;
+            } else {
+                c = c << 1;
+            }
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    return table;
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0;
+    for byte in data {
+        crc = CRC8_TABLE[(crc ^ *byte) as usize];
+    }
+    return crc;
+}
+
+fn crc16_update(crc: u16, byte: u8) -> u16 {
+    return CRC16_TABLE[((crc >> 8) as u8 ^ byte) as usize] ^ (crc << 8);
+}
+
+// Stream properties from STREAMINFO
+struct StreamInfo {
+    max_frame_length: usize,
+    sampling_rate: u32,
+    channels: u8,
+    bits_per_sample: u8,
+    // 0 if unknown
+    total_samples: u64,
+}
+
+fn read_streaminfo(block: &[u8]) -> StreamInfo {
+    return StreamInfo{
+        max_frame_length: ((block[7] as usize) << 16) | ((block[8] as usize) << 8) | block[9] as usize,
+        sampling_rate: ((block[10] as u32) << 12) | ((block[11] as u32) << 4) | ((block[12] as u32) >> 4),
+        channels: ((block[12] >> 1) & 0x07) + 1,
+        bits_per_sample: (((block[12] & 0x01) << 4) | (block[13] >> 4)) + 1,
+        total_samples: (((block[13] & 0x0F) as u64) << 32) | u32::from_be_bytes([block[14], block[15], block[16], block[17]]) as u64,
+    };
+}
+
+// Checks whether a valid frame header with a matching CRC-8 starts at frame_start,
+// returns the number of samples per channel and the number of channels in the frame if it does
+fn read_frame_header(data: &[u8], frame_start: usize) -> Option<(u32, u8)> {
+    if frame_start + 5 > data.len() {
+        return None;
+    }
+
+    let header: &[u8] = &data[frame_start..];
+    if u16::from_be_bytes([header[0], header[1]]) & FRAME_SYNC_MASK != FRAME_SYNC {
+        return None;
+    }
+
+    let block_size_code: u8 = header[2] >> 4;
+    let sampling_rate_code: u8 = header[2] & 0x0F;
+    let channel_assignment: u8 = header[3] >> 4;
+    let sample_size_code: u8 = (header[3] >> 1) & 0x07;
+    if block_size_code == 0 || sampling_rate_code == 0x0F || channel_assignment > 10 || sample_size_code == 3 || header[3] & 0x01 != 0 {
+        return None;
+    }
+
+    // UTF-8 like coded frame or sample number
+    let number_length: usize = match header[4] {
+        byte if byte & 0x80 == 0 => 1,
+        byte if byte & 0xE0 == 0xC0 => 2,
+        byte if byte & 0xF0 == 0xE0 => 3,
+        byte if byte & 0xF8 == 0xF0 => 4,
+        byte if byte & 0xFC == 0xF8 => 5,
+        byte if byte & 0xFE == 0xFC => 6,
+        0xFE => 7,
+        _ => return None,
+    };
+
+    let mut i: usize = 4 + number_length;
+    if frame_start + i > data.len() {
+        return None;
+    }
+    if header[5..i].iter().any(|byte| byte & 0xC0 != 0x80) {
+        return None;
+    }
+
+    let block_size: u32;
+    match block_size_code {
+        1 => block_size = 192,
+        2..=5 => block_size = 576 << (block_size_code - 2),
+        6 => {
+            if frame_start + i + 1 > data.len() {
+                return None;
+            }
+            block_size = header[i] as u32 + 1;
+            i += 1;
+        }
+        7 => {
+            if frame_start + i + 2 > data.len() {
+                return None;
+            }
+            block_size = u16::from_be_bytes([header[i], header[i + 1]]) as u32 + 1;
+            i += 2;
+        }
+        _ => block_size = 256 << (block_size_code - 8),
+    }
+
+    match sampling_rate_code {
+        12 => i += 1,
+        13 | 14 => i += 2,
+        _ => {}
+    }
+
+    if frame_start + i + 1 > data.len() || crc8(&header[..i]) != header[i] {
+        return None;
+    }
+
+    // independent channels or one of the stereo decorrelation modes
+    let channels: u8 = if channel_assignment < 8 {channel_assignment + 1} else {2};
+
+    return Some((block_size, channels));
+}
+
+// Tries to read a FLAC stream that starts exactly at start_index,
+// if valid flac bytes were found - returns exact positions of a stream.
+// Frames can't be measured without decoding them, so a frame is considered to end
+// where its CRC-16 checks out and either the next frame header follows
+// or STREAMINFO says there are no more samples
+pub fn rip_flac(data: &[u8], start_index: usize) -> Option<Position> {
+    if start_index + FLAC_IDENTIFIER.len() > data.len() {
+        return None;
+    }
+
+    if data[start_index..start_index + FLAC_IDENTIFIER.len()] != FLAC_IDENTIFIER {
+        return None;
+    }
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: ContentType::FLAC,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    // metadata blocks, STREAMINFO comes first
+    let mut stream_info: Option<StreamInfo> = None;
+    let mut i: usize = start_index + FLAC_IDENTIFIER.len();
+    loop {
+        if i + METADATA_BLOCK_HEADER_LENGTH > data.len() {
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            return Some(position);
+        }
+
+        let block_type: u8 = data[i] & !LAST_BLOCK_FLAG;
+        let block_length: usize = ((data[i + 1] as usize) << 16) | ((data[i + 2] as usize) << 8) | data[i + 3] as usize;
+        if stream_info.is_none() {
+            if block_type != STREAMINFO || block_length != STREAMINFO_LENGTH {
+                return None;
+            }
+            if i + METADATA_BLOCK_HEADER_LENGTH + STREAMINFO_LENGTH > data.len() {
+                position.end = data.len();
+                position.validity = Validity::TRUNCATED;
+                return Some(position);
+            }
+            stream_info = Some(read_streaminfo(&data[i + METADATA_BLOCK_HEADER_LENGTH..]));
+        }
+        else if block_type == STREAMINFO || block_type > PICTURE {
+            // only one STREAMINFO is allowed, other types are reserved
+            return None;
+        }
+
+        let is_last_block: bool = data[i] & LAST_BLOCK_FLAG != 0;
+        i += METADATA_BLOCK_HEADER_LENGTH + block_length;
+        if is_last_block {
+            break;
+        }
+    }
+
+    let stream_info: StreamInfo = stream_info?;
+    position.metadata.push(("sampling_rate", stream_info.sampling_rate.to_string()));
+    position.metadata.push(("channels", stream_info.channels.to_string()));
+    position.metadata.push(("bits_per_sample", stream_info.bits_per_sample.to_string()));
+    if stream_info.total_samples != 0 && stream_info.sampling_rate != 0 {
+        position.metadata.push(("samples", stream_info.total_samples.to_string()));
+        position.metadata.push(("duration", format!("{:.3}", stream_info.total_samples as f64 / stream_info.sampling_rate as f64)));
+    }
+
+    if i > data.len() {
+        position.end = data.len();
+        position.validity = Validity::TRUNCATED;
+        return Some(position);
+    }
+
+    // audio frames
+    let mut samples: u64 = 0;
+    let mut frame_start: usize = i;
+    while position.end == usize::MAX {
+        let block_size: u32;
+        let channels: u8;
+        match read_frame_header(data, frame_start) {
+            Some((size, channel_count)) => {
+                block_size = size;
+                channels = channel_count;
+            }
+            None => {
+                if data.len() - frame_start < 16 {
+                    // there may be a frame header further than the data goes
+                    position.end = data.len();
+                    position.validity = Validity::TRUNCATED;
+                }
+                else {
+                    // the stream ends prematurely and something else follows,
+                    // unless it was metadata only
+                    position.end = frame_start;
+                    if stream_info.total_samples != samples {
+                        position.validity = Validity::TRUNCATED;
+                    }
+                }
+                break;
+            }
+        }
+
+        samples += block_size as u64;
+        let is_last_frame: bool = stream_info.total_samples != 0 && samples >= stream_info.total_samples;
+
+        // a frame is never bigger than its samples stored verbatim (side channels take a bit more)
+        let mut max_frame_length: usize = block_size as usize * channels as usize * (stream_info.bits_per_sample as usize + 1) / 8 + FRAME_OVERHEAD;
+        if stream_info.max_frame_length != 0 {
+            max_frame_length = std::cmp::min(max_frame_length, stream_info.max_frame_length);
+        }
+
+        // look for the frame end byte by byte, crc of a frame with its own crc appended is 0
+        let scan_limit: usize = std::cmp::min(data.len(), frame_start + max_frame_length);
+        let mut possible_end: Option<usize> = None;
+        let mut next_frame_start: Option<usize> = None;
+        let mut crc: u16 = 0;
+        let mut p: usize = frame_start;
+        while p < scan_limit {
+            crc = crc16_update(crc, data[p]);
+            p += 1;
+
+            // header, at least one byte of subframes and the crc
+            if crc != 0 || p < frame_start + 7 {
+                continue;
+            }
+
+            if is_last_frame {
+                possible_end = Some(p);
+                break;
+            }
+            if read_frame_header(data, p).is_some() {
+                next_frame_start = Some(p);
+                break;
+            }
+            if possible_end.is_none() {
+                // the stream might end with this frame
+                possible_end = Some(p);
+            }
+        }
+
+        let ran_out_of_data: bool = p == data.len() && scan_limit < frame_start + max_frame_length;
+        match (next_frame_start, possible_end) {
+            (Some(next), _) => {
+                frame_start = next;
+            }
+            (None, Some(end)) if is_last_frame || (stream_info.total_samples == 0 && (!ran_out_of_data || end == data.len())) => {
+                position.end = end;
+            }
+            _ => {
+                if ran_out_of_data {
+                    // no end in sight, it might be further than the data goes
+                    position.end = data.len();
+                }
+                else {
+                    // the frame is broken, the stream can be trusted only up to it
+                    position.end = frame_start;
+                }
+                position.validity = Validity::TRUNCATED;
+            }
+        }
+    }
+
+    if samples > stream_info.total_samples && stream_info.total_samples != 0 {
+        position.validity = Validity::CORRUPT;
+    }
+
+    return Some(position);
+}
+
+pub struct FlacRipper;
+
+impl Ripper for FlacRipper {
+    fn name(&self) -> &'static str {
+        return "FLAC";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::FLAC;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::AUDIO;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&FLAC_IDENTIFIER];
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_flac(data, start_index);
+    }
+}
@@ -1 +1,4 @@
-pub mod mp3;
\ No newline at end of file
+pub mod mp3;
+pub mod ogg;
+pub mod flac;
+pub mod aac;
\ No newline at end of file
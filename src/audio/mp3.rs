@@ -18,12 +18,56 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::util::position::Position;
 use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
 
 const ID3V2_IDENTIFIER: [u8; 3] = [0x49, 0x44, 0x33];
 const ID3V2_HEADER_LENGTH: usize = 10;
+// ID3v2.4 footer is a copy of the header with "3DI" instead of "ID3"
+const ID3V2_FOOTER_LENGTH: usize = 10;
+const ID3V2_EXTENDED_HEADER_FLAG: u8 = 0x40;
+const ID3V2_FOOTER_FLAG: u8 = 0x10;
+const ID3V1_IDENTIFIER: [u8; 3] = [0x54, 0x41, 0x47];
+const ID3V1_LENGTH: usize = 128;
+const APE_IDENTIFIER: [u8; 8] = [0x41, 0x50, 0x45, 0x54, 0x41, 0x47, 0x45, 0x58];
+const APE_HEADER_LENGTH: usize = 32;
+const APE_CONTAINS_HEADER_FLAG: u32 = 0x80000000;
+const APE_IS_HEADER_FLAG: u32 = 0x20000000;
+// APE item keys are this long at most
+const MAX_APE_KEY_LENGTH: usize = 255;
 const MP3_HEADER_LENGTH: usize = 4;
+pub const DEFAULT_MIN_FRAMES: usize = 5;
 
-// bitrate table for mpeg Version+Layer
+// ID3v2 tag or frame sync followed by every valid version and layer combination
+const MP3_IDENTIFIERS: [&[u8]; 19] = [
+    &ID3V2_IDENTIFIER,
+    // mpeg 2.5
+    &[0xFF, 0xE2], &[0xFF, 0xE3], &[0xFF, 0xE4], &[0xFF, 0xE5], &[0xFF, 0xE6], &[0xFF, 0xE7],
+    // mpeg 2
+    &[0xFF, 0xF2], &[0xFF, 0xF3], &[0xFF, 0xF4], &[0xFF, 0xF5], &[0xFF, 0xF6], &[0xFF, 0xF7],
+    // mpeg 1
+    &[0xFF, 0xFA], &[0xFF, 0xFB], &[0xFF, 0xFC], &[0xFF, 0xFD], &[0xFF, 0xFE], &[0xFF, 0xFF],
+];
+
+// the biggest possible free-format frame (mpeg 2.5 layer III at 640 kbps and 8 kHz)
+const MAX_FREE_FORMAT_FRAME_SIZE: usize = 5761;
+
+const XING_IDENTIFIER: [u8; 4] = [0x58, 0x69, 0x6E, 0x67];
+const INFO_IDENTIFIER: [u8; 4] = [0x49, 0x6E, 0x66, 0x6F];
+const VBRI_IDENTIFIER: [u8; 4] = [0x56, 0x42, 0x52, 0x49];
+const LAME_IDENTIFIER: [u8; 4] = [0x4C, 0x41, 0x4D, 0x45];
+// VBRI header always sits right after 32 bytes of side information
+const VBRI_OFFSET: usize = MP3_HEADER_LENGTH + 32;
+const XING_FRAMES_FLAG: u32 = 0x1;
+const XING_BYTES_FLAG: u32 = 0x2;
+const XING_TOC_FLAG: u32 = 0x4;
+const XING_QUALITY_FLAG: u32 = 0x8;
+const XING_TOC_LENGTH: usize = 100;
+// music length field offset inside of LAME extension
+const LAME_MUSIC_LENGTH_OFFSET: usize = 28;
+
+// bitrate table for mpeg Version+Layer, 0 means free-format
 const MP3_BITRATE_TABLE: [[[u32; 15]; 3]; 2] = [
     // mpegv1
     [
@@ -54,19 +98,22 @@ const MP3_BITRATE_TABLE: [[[u32; 15]; 3]; 2] = [
             96_000, 112_000, 128_000, 144_000, 160_000,
             176_000, 192_000, 224_000, 256_000,
         ],
-        // layer III
+        // layer II
         [
             0, 8_000, 16_000, 24_000, 32_000, 40_000, 48_000,
             56_000, 64_000, 80_000, 96_000, 112_000, 128_000,
             144_000, 160_000,
         ],
-        // layer III
+        // layer III (same as layer II)
         [
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 8_000, 16_000, 24_000, 32_000, 40_000, 48_000,
+            56_000, 64_000, 80_000, 96_000, 112_000, 128_000,
+            144_000, 160_000,
         ],
     ],
 ];
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Layer {
     I,
     II,
@@ -80,12 +127,35 @@ enum MpegVersion {
     V2_5,
 }
 
+impl Layer {
+    fn name(&self) -> &'static str {
+        match self {
+            Layer::I => return "I",
+            Layer::II => return "II",
+            Layer::III => return "III",
+        }
+    }
+}
+
+impl MpegVersion {
+    fn name(&self) -> &'static str {
+        match self {
+            MpegVersion::V1 => return "1",
+            MpegVersion::V2 => return "2",
+            MpegVersion::V2_5 => return "2.5",
+        }
+    }
+}
+
 struct MP3Header {
     bitrate: u32,
     layer: Layer,
     version: MpegVersion,
     sampling_rate: u32,
     padding: bool,
+    // 16-bit CRC follows the header
+    protected: bool,
+    mono: bool,
 }
 
 impl MP3Header {
@@ -115,15 +185,17 @@ impl MP3Header {
         }
 
         // calculate bitrate
-        let bitrate: u32;
-        match ((header & 0xF000) >> 12, &version, &layer) {
-            (n, MpegVersion::V1, Layer::I) => bitrate = MP3_BITRATE_TABLE[0][0][n as usize],
-            (n, MpegVersion::V1, Layer::II) => bitrate = MP3_BITRATE_TABLE[0][1][n as usize],
-            (n, MpegVersion::V1, Layer::III) => bitrate = MP3_BITRATE_TABLE[0][2][n as usize],
-            (n, MpegVersion::V2|MpegVersion::V2_5, Layer::I) => bitrate = MP3_BITRATE_TABLE[1][0][n as usize],
-            (n, MpegVersion::V2|MpegVersion::V2_5, Layer::III) => bitrate = MP3_BITRATE_TABLE[1][1][n as usize],
-            _ => return Err("invalid or too tricky frame header to calculate bitrate"),
+        if (header & 0xF000) >> 12 == 0b1111 {
+            return Err("invalid bitrate index");
+        }
+        let version_row: usize = if version == MpegVersion::V1 {0} else {1};
+        let layer_column: usize;
+        match layer {
+            Layer::I => layer_column = 0,
+            Layer::II => layer_column = 1,
+            Layer::III => layer_column = 2,
         }
+        let bitrate: u32 = MP3_BITRATE_TABLE[version_row][layer_column][((header & 0xF000) >> 12) as usize];
 
         // sample rate
         let sampling_rate: u32;
@@ -141,6 +213,8 @@ impl MP3Header {
         }
 
         let padding: bool = header & 0x200 != 0;
+        let protected: bool = header & 0x10000 == 0;
+        let mono: bool = (header & 0xC0) >> 6 == 0b11;
 
         return Ok(MP3Header{
             bitrate: bitrate,
@@ -148,86 +222,572 @@ impl MP3Header {
             version: version,
             sampling_rate: sampling_rate,
             padding: padding,
+            protected: protected,
+            mono: mono,
         });
     }
 
-    fn frame_size(&self) -> usize {
-        return {
-            (if self.version == MpegVersion::V1 {144} else {72} *
-             self.bitrate /
-             self.sampling_rate) as usize + if self.padding {1} else {0} 
-        };
+    // Whether both headers could belong to the same stream
+    fn is_compatible(&self, other: &MP3Header) -> bool {
+        return self.version == other.version &&
+            self.layer == other.layer &&
+            self.sampling_rate == other.sampling_rate;
+    }
+
+    // Where Xing/Info header would be in a layer III frame
+    fn xing_offset(&self) -> usize {
+        let side_information_size: usize;
+        match (&self.version, self.mono) {
+            (MpegVersion::V1, true) => side_information_size = 17,
+            (MpegVersion::V1, false) => side_information_size = 32,
+            (MpegVersion::V2|MpegVersion::V2_5, true) => side_information_size = 9,
+            (MpegVersion::V2|MpegVersion::V2_5, false) => side_information_size = 17,
+        }
+
+        return MP3_HEADER_LENGTH + if self.protected {2} else {0} + side_information_size;
+    }
+
+    fn is_free_format(&self) -> bool {
+        return self.bitrate == 0;
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (&self.layer, &self.version) {
+            (Layer::I, _) => return 384,
+            (Layer::II, _) => return 1152,
+            (Layer::III, MpegVersion::V1) => return 1152,
+            (Layer::III, MpegVersion::V2|MpegVersion::V2_5) => return 576,
+        }
+    }
+
+    // layer I frames are measured in 4-byte slots
+    fn slot_size(&self) -> usize {
+        return if self.layer == Layer::I {4} else {1};
+    }
+
+    fn padding_size(&self) -> usize {
+        return if self.padding {self.slot_size()} else {0};
+    }
+
+    // Frame length in bytes, free-format frames have no bitrate to calculate it from
+    fn frame_size(&self) -> Option<usize> {
+        if self.is_free_format() {
+            return None;
+        }
+
+        let slots: u32 = self.samples_per_frame() / 8 * self.bitrate / self.sampling_rate / self.slot_size() as u32;
+        return Some(slots as usize * self.slot_size() + self.padding_size());
     }
 }
 
+// Free-format streams keep the same frame length (apart from padding),
+// so it is the distance to the next matching free-format header.
+// Returns frame length without padding
+fn find_free_format_frame_size(data: &[u8], header: &MP3Header, start_index: usize) -> Option<usize> {
+    let search_end: usize = std::cmp::min(data.len(), start_index + MAX_FREE_FORMAT_FRAME_SIZE + MP3_HEADER_LENGTH);
+    let mut mp3_header_bytes: [u8; MP3_HEADER_LENGTH] = [0; MP3_HEADER_LENGTH];
+    for i in start_index + MP3_HEADER_LENGTH..search_end.saturating_sub(MP3_HEADER_LENGTH) {
+        if data[i] != 0xFF {
+            continue;
+        }
+
+        mp3_header_bytes.copy_from_slice(&data[i..i + MP3_HEADER_LENGTH]);
+        if let Ok(next_header) = MP3Header::from_bytes(&mp3_header_bytes) {
+            if next_header.is_free_format() && header.is_compatible(&next_header) {
+                return Some(i - start_index - header.padding_size());
+            }
+        }
+    }
+
+    return None;
+}
 
-pub fn rip_mp3(data: &[u8], start_index: usize) -> Option<Position> {
-    if data.len() < ID3V2_HEADER_LENGTH + MP3_HEADER_LENGTH + 1 ||
-        start_index + ID3V2_HEADER_LENGTH + MP3_HEADER_LENGTH + 1 >= data.len() {
+fn read_u32_be(data: &[u8], index: usize) -> Option<u32> {
+    if index + 4 > data.len() {
         return None;
     }
+    return Some(u32::from_be_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]));
+}
 
-    let mut position: Position = Position{
-        start: usize::MAX,
-        end: usize::MAX,
-        content_type: ContentType::MP3,
-    };
+// Converts syncsafe integer (7 bits per byte) to a normal one
+fn from_syncsafe(bytes: &[u8]) -> u32 {
+    let mut value: u32 = 0;
+    for byte in bytes {
+        value = value << 7;
+        value = value | (*byte & 0x7F) as u32;
+    }
+    return value;
+}
 
-    for i in start_index..data.len() {
-        if i < ID3V2_HEADER_LENGTH && position.start == usize::MAX {
-            if data[i..i + ID3V2_IDENTIFIER.len()] == ID3V2_IDENTIFIER {
-                // found ID3v2 tag (the beginning of the MP3 file)
-                // get tag length
-                let mut tag_length_bytes: [u8; 4] = [0; 4];
-                for j in 0..4 {
-                    tag_length_bytes[j] = data[i+ID3V2_IDENTIFIER.len()+3+j];
-                }
-                // convert syncsafe integer to a normal one
-                let mut tag_length: u32 = 0;
-                for j in 0..4 {
-                    tag_length = tag_length << 7;
-                    tag_length = tag_length | tag_length_bytes[j] as u32;
-                }
+// Returns the whole length of an ID3v2 tag at start_index (header, extended header,
+// frames, padding and footer). None if it's not a real tag
+fn id3v2_tag_length(data: &[u8], start_index: usize) -> Option<usize> {
+    if start_index + ID3V2_HEADER_LENGTH > data.len() ||
+        data[start_index..start_index + ID3V2_IDENTIFIER.len()] != ID3V2_IDENTIFIER {
+        return None;
+    }
 
-                let id3v2_end_index: usize = i + ID3V2_HEADER_LENGTH + tag_length as usize;
-                if id3v2_end_index + MP3_HEADER_LENGTH > data.len() - 1 {
-                    println!("not enough data length");
-                    // strange: there's a valid ID3 tag but not enough data to store any music
-                    break;
+    // ID3v2.2 - ID3v2.4 with syncsafe size bytes, otherwise "ID3" is just a coincidence
+    let major_version: u8 = data[start_index + 3];
+    let revision: u8 = data[start_index + 4];
+    let flags: u8 = data[start_index + 5];
+    if !(2..=4).contains(&major_version) || revision == 0xFF ||
+        data[start_index + 6..start_index + ID3V2_HEADER_LENGTH].iter().any(|byte| *byte >= 0x80) {
+        return None;
+    }
+
+    // size excludes header and footer
+    let tag_size: usize = from_syncsafe(&data[start_index + 6..start_index + ID3V2_HEADER_LENGTH]) as usize;
+
+    // extended header counts towards the size, make sure it fits
+    let extended_header_start: usize = start_index + ID3V2_HEADER_LENGTH;
+    if major_version >= 3 && flags & ID3V2_EXTENDED_HEADER_FLAG != 0 && extended_header_start + 4 <= data.len() {
+        let size_bytes: &[u8] = &data[extended_header_start..extended_header_start + 4];
+        let extended_header_size: usize;
+        if major_version == 4 {
+            // syncsafe, includes itself
+            extended_header_size = from_syncsafe(size_bytes) as usize;
+        } else {
+            // plain, excludes itself
+            extended_header_size = u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]) as usize + 4;
+        }
+
+        if extended_header_size < 6 || extended_header_size > tag_size {
+            return None;
+        }
+    }
+
+    let mut tag_length: usize = ID3V2_HEADER_LENGTH + tag_size;
+    if major_version == 4 && flags & ID3V2_FOOTER_FLAG != 0 {
+        tag_length += ID3V2_FOOTER_LENGTH;
+    }
+
+    return Some(tag_length);
+}
+
+// Walks items of an APEv2 tag without a header that starts at the beginning of data,
+// returns the length of the tag if the items end with a footer whose size points back at the start
+fn headerless_ape_tag_length(data: &[u8]) -> Option<usize> {
+    let mut i: usize = 0;
+    let mut item_count: u32 = 0;
+    while !data[i..].starts_with(&APE_IDENTIFIER) {
+        // value size, flags, zero-terminated printable key and the value
+        if i + 8 > data.len() {
+            return None;
+        }
+        let value_size: usize = u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+
+        let key_start: usize = i + 8;
+        let key_search_end: usize = std::cmp::min(data.len(), key_start + MAX_APE_KEY_LENGTH + 1);
+        let key_length: usize = data[key_start..key_search_end].iter().position(|byte| *byte == 0)?;
+        if key_length < 2 || !data[key_start..key_start + key_length].iter().all(|byte| (0x20..=0x7E).contains(byte)) {
+            return None;
+        }
+
+        i = key_start + key_length + 1 + value_size;
+        if i > data.len() {
+            return None;
+        }
+        item_count += 1;
+    }
+
+    if i + APE_HEADER_LENGTH > data.len() {
+        return None;
+    }
+    let footer: &[u8] = &data[i..i + APE_HEADER_LENGTH];
+    let size: usize = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]) as usize;
+    let footer_item_count: u32 = u32::from_le_bytes([footer[16], footer[17], footer[18], footer[19]]);
+    let flags: u32 = u32::from_le_bytes([footer[20], footer[21], footer[22], footer[23]]);
+    // size counts items and the footer
+    if flags & APE_IS_HEADER_FLAG != 0 || flags & APE_CONTAINS_HEADER_FLAG != 0 ||
+        size != i + APE_HEADER_LENGTH || footer_item_count != item_count {
+        return None;
+    }
+
+    return Some(size);
+}
+
+// Finds where tags that follow the last frame end: APEv2, ID3v1 and appended ID3v2.4.
+// Returns the new end and whether the data ended in the middle of a tag
+fn skip_trailing_tags(data: &[u8], end: usize) -> (usize, bool) {
+    let mut tags_end: usize = end;
+    loop {
+        let remaining: &[u8] = &data[tags_end..];
+
+        // APEv2 with a header
+        if remaining.starts_with(&APE_IDENTIFIER) && remaining.len() >= APE_HEADER_LENGTH {
+            let size: usize = u32::from_le_bytes([remaining[12], remaining[13], remaining[14], remaining[15]]) as usize;
+            let flags: u32 = u32::from_le_bytes([remaining[20], remaining[21], remaining[22], remaining[23]]);
+            if flags & APE_IS_HEADER_FLAG != 0 && size >= APE_HEADER_LENGTH {
+                // size counts items and the footer
+                tags_end += APE_HEADER_LENGTH + size;
+                if tags_end > data.len() {
+                    return (data.len(), true);
                 }
-                position.start = i;
+                continue;
+            }
+        }
 
-                position.end = id3v2_end_index;
-                break;
+        // APEv2 without a header, its items have to lead right to the footer
+        if let Some(tag_length) = headerless_ape_tag_length(remaining) {
+            tags_end += tag_length;
+            continue;
+        }
+
+        // ID3v1
+        if remaining.starts_with(&ID3V1_IDENTIFIER) {
+            if remaining.len() < ID3V1_LENGTH {
+                return (data.len(), true);
+            }
+            tags_end += ID3V1_LENGTH;
+            continue;
+        }
+
+        // ID3v2.4 with a footer can be appended as well, otherwise
+        // it's a tag of the next file
+        let is_appended_id3v2: bool = remaining.len() >= ID3V2_HEADER_LENGTH &&
+            remaining[3] == 4 && remaining[5] & ID3V2_FOOTER_FLAG != 0;
+        if let (true, Some(tag_length)) = (is_appended_id3v2, id3v2_tag_length(data, tags_end)) {
+            tags_end += tag_length;
+            if tags_end > data.len() {
+                return (data.len(), true);
             }
+            continue;
         }
+
+        break;
     }
 
-    // return None immediately if id3 tag was not found
-    if position.start == usize::MAX {
+    return (tags_end, false);
+}
+
+// What the first frame of a stream tells about the whole stream
+struct VbrHeader {
+    // amount of audio frames
+    frame_count: Option<u32>,
+    // stream length in bytes starting from the first frame
+    byte_count: Option<u32>,
+}
+
+// Looks for Xing/Info (possibly with LAME extension) or VBRI header
+// inside of the frame that starts at frame_start
+fn read_vbr_header(data: &[u8], frame_start: usize) -> Option<VbrHeader> {
+    if frame_start + MP3_HEADER_LENGTH > data.len() {
         return None;
     }
 
-    // try to extract mp3 frames
     let mut mp3_header_bytes: [u8; MP3_HEADER_LENGTH] = [0; MP3_HEADER_LENGTH];
-    while position.end < data.len() - MP3_HEADER_LENGTH {
-        for j in 0..MP3_HEADER_LENGTH {
-            mp3_header_bytes[j] = data[position.end + j];
+    mp3_header_bytes.copy_from_slice(&data[frame_start..frame_start + MP3_HEADER_LENGTH]);
+    let header: MP3Header = MP3Header::from_bytes(&mp3_header_bytes).ok()?;
+    if header.layer != Layer::III {
+        return None;
+    }
+
+    let xing_start: usize = frame_start + header.xing_offset();
+    if xing_start + 8 <= data.len() &&
+        (data[xing_start..xing_start + 4] == XING_IDENTIFIER || data[xing_start..xing_start + 4] == INFO_IDENTIFIER) {
+        let flags: u32 = read_u32_be(data, xing_start + 4)?;
+        let mut vbr_header: VbrHeader = VbrHeader{
+            frame_count: None,
+            byte_count: None,
+        };
+
+        let mut field_index: usize = xing_start + 8;
+        if flags & XING_FRAMES_FLAG != 0 {
+            vbr_header.frame_count = Some(read_u32_be(data, field_index)?);
+            field_index += 4;
+        }
+        if flags & XING_BYTES_FLAG != 0 {
+            vbr_header.byte_count = Some(read_u32_be(data, field_index)?);
+            field_index += 4;
+        }
+        if flags & XING_TOC_FLAG != 0 {
+            field_index += XING_TOC_LENGTH;
+        }
+        if flags & XING_QUALITY_FLAG != 0 {
+            field_index += 4;
         }
 
+        // LAME knows the exact music length
+        if field_index + 4 <= data.len() && data[field_index..field_index + 4] == LAME_IDENTIFIER {
+            if let Some(music_length) = read_u32_be(data, field_index + LAME_MUSIC_LENGTH_OFFSET) {
+                if music_length != 0 {
+                    vbr_header.byte_count = Some(music_length);
+                }
+            }
+        }
+
+        return Some(vbr_header);
+    }
+
+    let vbri_start: usize = frame_start + VBRI_OFFSET;
+    if vbri_start + 18 <= data.len() && data[vbri_start..vbri_start + 4] == VBRI_IDENTIFIER {
+        // version, delay and quality come first
+        return Some(VbrHeader{
+            byte_count: Some(read_u32_be(data, vbri_start + 10)?),
+            frame_count: Some(read_u32_be(data, vbri_start + 14)?),
+        });
+    }
+
+    return None;
+}
+
+// A chain of consecutive frames
+struct FrameRun {
+    end: usize,
+    frame_count: usize,
+    // data ended before the chain did
+    ran_out: bool,
+    first_header: Option<MP3Header>,
+}
+
+// Follows frames starting at start_index for as long as each next frame
+// begins right where the previous one ends and looks like the first one.
+// Stops once stop_at is reached
+fn walk_frames(data: &[u8], start_index: usize, stop_at: usize) -> FrameRun {
+    let mut run: FrameRun = FrameRun{
+        end: start_index,
+        frame_count: 0,
+        ran_out: false,
+        first_header: None,
+    };
+
+    let mut free_format_frame_size: usize = 0;
+    let mut mp3_header_bytes: [u8; MP3_HEADER_LENGTH] = [0; MP3_HEADER_LENGTH];
+    while run.end < stop_at && run.end + MP3_HEADER_LENGTH <= data.len() {
+        mp3_header_bytes.copy_from_slice(&data[run.end..run.end + MP3_HEADER_LENGTH]);
+
+        let header: MP3Header;
         match MP3Header::from_bytes(&mp3_header_bytes) {
-            Ok(header) => {
-                position.end += header.frame_size();
+            Ok(frame_header) => {
+                header = frame_header;
             }
             Err(_) => {
                 break;
             }
         }
+
+        match &run.first_header {
+            Some(first) => {
+                if !first.is_compatible(&header) || first.is_free_format() != header.is_free_format() {
+                    break;
+                }
+            }
+            None => {
+                if header.is_free_format() {
+                    match find_free_format_frame_size(data, &header, run.end) {
+                        Some(frame_size) => free_format_frame_size = frame_size,
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        let frame_size: usize;
+        match header.frame_size() {
+            Some(size) => frame_size = size,
+            None => frame_size = free_format_frame_size + header.padding_size(),
+        }
+        if frame_size <= MP3_HEADER_LENGTH {
+            break;
+        }
+
+        if run.first_header.is_none() {
+            run.first_header = Some(header);
+        }
+
+        run.end += frame_size;
+        run.frame_count += 1;
+    }
+
+    // the last frame might not fit entirely, no matter how long the stream claims to be
+    if run.end > data.len() {
+        run.end = data.len();
+        run.ran_out = true;
     }
 
-    if position.start == usize::MAX || position.end == usize::MAX || position.end <= position.start {
+    // or there's not enough data left to tell whether another frame follows
+    if run.end < stop_at && run.end != data.len() && run.end + MP3_HEADER_LENGTH > data.len() {
+        run.end = data.len();
+        run.ran_out = true;
+    }
+
+    return run;
+}
+
+// Tries to read an mp3 that starts exactly at start_index either with an ID3v2 tag
+// or straight with a frame. Untagged streams must have at least min_frames chained frames.
+// If valid frames were found - returns exact positions of the audio
+pub fn rip_mp3(data: &[u8], start_index: usize, min_frames: usize) -> Option<Position> {
+    if start_index + MP3_HEADER_LENGTH > data.len() {
+        return None;
+    }
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: start_index,
+        content_type: ContentType::MP3,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    let frames_start: usize;
+    let required_frames: usize;
+    if data[start_index..start_index + ID3V2_IDENTIFIER.len()] == ID3V2_IDENTIFIER {
+        if start_index + ID3V2_HEADER_LENGTH > data.len() {
+            return None;
+        }
+
+        let id3v2_length: usize = id3v2_tag_length(data, start_index)?;
+
+        // found ID3v2 tag (the beginning of the MP3 file)
+        let id3v2_end_index: usize = start_index + id3v2_length;
+        if id3v2_end_index > data.len() {
+            // the tag itself does not fit
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            return Some(position);
+        }
+
+        frames_start = id3v2_end_index;
+        required_frames = 0;
+    } else {
+        // no tag, so there must be enough frames to be sure it's not a coincidence
+        frames_start = start_index;
+        required_frames = min_frames;
+    }
+
+    // the first frame might say how long the stream is
+    let vbr_header: Option<VbrHeader> = read_vbr_header(data, frames_start);
+    let mut expected_end: usize = usize::MAX;
+    if let Some(VbrHeader{byte_count: Some(byte_count), ..}) = vbr_header {
+        expected_end = frames_start.saturating_add(byte_count as usize);
+    }
+
+    // try to extract mp3 frames
+    let run: FrameRun = walk_frames(data, frames_start, expected_end);
+    if run.frame_count < required_frames {
+        return None;
+    }
+
+    position.end = run.end;
+    if run.ran_out {
+        position.validity = Validity::TRUNCATED;
+    } else {
+        // tags can follow the frames as well
+        let (tags_end, tags_ran_out): (usize, bool) = skip_trailing_tags(data, run.end);
+        position.end = tags_end;
+        if tags_ran_out {
+            position.validity = Validity::TRUNCATED;
+        }
+    }
+
+    // cross-check with what the stream says about itself
+    if let Some(vbr_header) = vbr_header {
+        if expected_end != usize::MAX && run.end < expected_end {
+            // frames stopped earlier than they should have
+            position.validity = Validity::TRUNCATED;
+        } else if expected_end != usize::MAX && run.end > expected_end && !run.ran_out {
+            // declared length does not fall on a frame boundary
+            position.validity = Validity::CORRUPT;
+        }
+
+        if let Some(frame_count) = vbr_header.frame_count {
+            // the header frame itself may or may not be counted
+            let frame_count: usize = frame_count as usize;
+            if run.frame_count < frame_count {
+                position.validity = Validity::TRUNCATED;
+            } else if run.frame_count > frame_count + 1 && position.validity == Validity::VALID {
+                position.validity = Validity::CORRUPT;
+            }
+        }
+    }
+
+    if let Some(header) = &run.first_header {
+        // older layers get their own extensions
+        match header.layer {
+            Layer::I => position.content_type = ContentType::MP1,
+            Layer::II => position.content_type = ContentType::MP2,
+            Layer::III => {}
+        }
+
+        position.metadata.push(("version", String::from(header.version.name())));
+        position.metadata.push(("layer", String::from(header.layer.name())));
+        position.metadata.push(("sampling_rate", header.sampling_rate.to_string()));
+        position.metadata.push(("channels", String::from(if header.mono {"1"} else {"2"})));
+    }
+    position.metadata.push(("frames", run.frame_count.to_string()));
+
+    if position.end <= position.start {
         return None;
     }
 
     return Some(position);
-}
\ No newline at end of file
+}
+
+pub struct Mp3Ripper {
+    // how many frames an untagged stream must have
+    pub min_frames: usize,
+}
+
+impl Ripper for Mp3Ripper {
+    fn name(&self) -> &'static str {
+        return "MP3";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::MP3;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::AUDIO;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &MP3_IDENTIFIERS;
+    }
+
+    // every frame starts with a sync, no need to look at each of them again
+    fn can_nest(&self) -> bool {
+        return false;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_mp3(data, start_index, self.min_frames);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // mpeg 1 layer III, 128 kbps, 44100 Hz, stereo, no padding: 417 bytes per frame
+    const FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+    const FRAME_SIZE: usize = 417;
+
+    #[test]
+    fn truncated_xing_stream_ends_with_data() {
+        // ID3v2.3 tag without frames
+        let mut data: Vec<u8> = vec![0x49, 0x44, 0x33, 3, 0, 0, 0, 0, 0, 0];
+
+        // Xing frame claiming 3 frames and 1000 bytes
+        let mut xing_frame: Vec<u8> = vec![0; FRAME_SIZE];
+        xing_frame[..4].copy_from_slice(&FRAME_HEADER);
+        xing_frame[36..40].copy_from_slice(&XING_IDENTIFIER);
+        xing_frame[40..44].copy_from_slice(&(XING_FRAMES_FLAG | XING_BYTES_FLAG).to_be_bytes());
+        xing_frame[44..48].copy_from_slice(&3u32.to_be_bytes());
+        xing_frame[48..52].copy_from_slice(&1000u32.to_be_bytes());
+        data.extend_from_slice(&xing_frame);
+
+        // a whole frame and a cut off one
+        let mut frame: Vec<u8> = vec![0; FRAME_SIZE];
+        frame[..4].copy_from_slice(&FRAME_HEADER);
+        data.extend_from_slice(&frame);
+        data.extend_from_slice(&frame[..100]);
+
+        let position: Position = rip_mp3(&data, 0, DEFAULT_MIN_FRAMES).expect("stream was not found");
+        assert_eq!(position.end, data.len());
+        assert_eq!(position.validity, Validity::TRUNCATED);
+    }
+}
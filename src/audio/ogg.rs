@@ -0,0 +1,269 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+use crate::util::crc32::ogg_crc32_update;
+
+const OGG_IDENTIFIER: [u8; 4] = *b"OggS";
+// everything before the segment table
+const PAGE_HEADER_LENGTH: usize = 27;
+const CRC_OFFSET: usize = 22;
+
+// header type flags
+const CONTINUED_PACKET: u8 = 0x01;
+const BEGINNING_OF_STREAM: u8 = 0x02;
+const END_OF_STREAM: u8 = 0x04;
+
+// Parsed page header
+struct OggPage {
+    header_type: u8,
+    serial: u32,
+    sequence: u32,
+    stored_crc: u32,
+    // index right after the segment table
+    body_start: usize,
+    // index right after the page
+    end: usize,
+}
+
+// Reads a page header at page_start, returns None if there's no page.
+// Page end is past data.len() if data ends before the page does
+fn read_page(data: &[u8], page_start: usize) -> Option<OggPage> {
+    let header: &[u8] = &data[page_start..page_start + PAGE_HEADER_LENGTH];
+    if header[0..4] != OGG_IDENTIFIER || header[4] != 0 || header[5] & !0x07 != 0 {
+        return None;
+    }
+
+    let segment_count: usize = header[26] as usize;
+    let body_start: usize = page_start + PAGE_HEADER_LENGTH + segment_count;
+    let mut body_length: usize = 0;
+    if body_start <= data.len() {
+        body_length = data[page_start + PAGE_HEADER_LENGTH..body_start]
+            .iter()
+            .map(|lacing_value| *lacing_value as usize)
+            .sum();
+    }
+
+    return Some(OggPage{
+        header_type: header[5],
+        serial: u32::from_le_bytes([header[14], header[15], header[16], header[17]]),
+        sequence: u32::from_le_bytes([header[18], header[19], header[20], header[21]]),
+        stored_crc: u32::from_le_bytes([header[22], header[23], header[24], header[25]]),
+        body_start: body_start,
+        end: body_start + body_length,
+    });
+}
+
+// Calculates the crc of a complete page as if its crc field was zeroed
+fn page_crc(data: &[u8], page_start: usize, page_end: usize) -> u32 {
+    let mut crc: u32 = ogg_crc32_update(0, &data[page_start..page_start + CRC_OFFSET]);
+    crc = ogg_crc32_update(crc, &[0; 4]);
+    crc = ogg_crc32_update(crc, &data[page_start + CRC_OFFSET + 4..page_end]);
+    return crc;
+}
+
+// Works out the codec from the identification packet that begins the first page
+// and puts what it says about the audio into metadata
+fn identify_codec(position: &mut Position, packet: &[u8]) {
+    if packet.starts_with(b"\x01vorbis") && packet.len() >= 16 {
+        position.metadata.push(("codec", String::from("Vorbis")));
+        position.metadata.push(("channels", packet[11].to_string()));
+        position.metadata.push(("sampling_rate", u32::from_le_bytes([packet[12], packet[13], packet[14], packet[15]]).to_string()));
+    }
+    else if packet.starts_with(b"OpusHead") && packet.len() >= 16 {
+        position.content_type = ContentType::OPUS;
+        position.metadata.push(("codec", String::from("Opus")));
+        position.metadata.push(("channels", packet[9].to_string()));
+        position.metadata.push(("sampling_rate", u32::from_le_bytes([packet[12], packet[13], packet[14], packet[15]]).to_string()));
+    }
+    else if packet.starts_with(b"Speex   ") && packet.len() >= 52 {
+        position.metadata.push(("codec", String::from("Speex")));
+        position.metadata.push(("channels", u32::from_le_bytes([packet[48], packet[49], packet[50], packet[51]]).to_string()));
+        position.metadata.push(("sampling_rate", u32::from_le_bytes([packet[36], packet[37], packet[38], packet[39]]).to_string()));
+    }
+    else if packet.starts_with(b"\x7FFLAC") && packet.len() >= 34 {
+        // mapping header, "fLaC", STREAMINFO block header, then STREAMINFO itself
+        let streaminfo: &[u8] = &packet[17..];
+        let sampling_rate: u32 = ((streaminfo[10] as u32) << 12) | ((streaminfo[11] as u32) << 4) | ((streaminfo[12] as u32) >> 4);
+        position.metadata.push(("codec", String::from("FLAC")));
+        position.metadata.push(("channels", (((streaminfo[12] >> 1) & 0x07) + 1).to_string()));
+        position.metadata.push(("sampling_rate", sampling_rate.to_string()));
+    }
+}
+
+// Tries to read an Ogg stream that starts exactly at start_index,
+// if valid ogg pages were found - returns exact positions of a stream.
+// Pages are followed from the beginning of every logical stream multiplexed
+// at the start to the end of the last of them. Pages with broken crc or lost pages
+// make the stream corrupt, ones that break off into something else - truncated
+pub fn rip_ogg(data: &[u8], start_index: usize) -> Option<Position> {
+    if start_index + OGG_IDENTIFIER.len() > data.len() {
+        return None;
+    }
+
+    if data[start_index..start_index + OGG_IDENTIFIER.len()] != OGG_IDENTIFIER {
+        return None;
+    }
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: ContentType::OGG,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    // serials of logical streams that have not ended yet and their last page sequence numbers
+    let mut open_streams: Vec<(u32, u32)> = Vec::new();
+    let mut may_begin_streams: bool = true;
+
+    let mut page_start: usize = start_index;
+    while position.end == usize::MAX {
+        if page_start + PAGE_HEADER_LENGTH > data.len() {
+            let rest: &[u8] = &data[page_start..];
+            if page_start == start_index || OGG_IDENTIFIER.starts_with(&rest[..std::cmp::min(rest.len(), OGG_IDENTIFIER.len())]) {
+                // no end in sight, it might be further than the data goes
+                position.end = data.len();
+            } else {
+                position.end = page_start;
+            }
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        let page: OggPage;
+        match read_page(data, page_start) {
+            Some(p) => {
+                page = p;
+            }
+            None => {
+                if page_start == start_index {
+                    return None;
+                }
+                // the stream ends prematurely and something else follows
+                position.end = page_start;
+                position.validity = Validity::TRUNCATED;
+                break;
+            }
+        }
+
+        if page.end > data.len() {
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        let crc_is_valid: bool = page_crc(data, page_start, page.end) == page.stored_crc;
+
+        if page.header_type & BEGINNING_OF_STREAM != 0 {
+            if !may_begin_streams || open_streams.iter().any(|(serial, _)| *serial == page.serial) {
+                // a chained stream begins, it's a different piece of content
+                position.end = page_start;
+                break;
+            }
+
+            if page_start == start_index {
+                if !crc_is_valid || page.header_type & CONTINUED_PACKET != 0 {
+                    // such a broken first page is more likely a coincidence
+                    return None;
+                }
+                identify_codec(&mut position, &data[page.body_start..page.end]);
+            }
+            open_streams.push((page.serial, page.sequence));
+        }
+        else {
+            if page_start == start_index {
+                // not the beginning of a stream
+                return None;
+            }
+            may_begin_streams = false;
+
+            match open_streams.iter_mut().find(|(serial, _)| *serial == page.serial) {
+                Some(stream) => {
+                    if page.sequence != stream.1.wrapping_add(1) {
+                        // some pages were lost
+                        position.validity = Validity::CORRUPT;
+                    }
+                    stream.1 = page.sequence;
+                }
+                None => {
+                    // a page of some other stream
+                    position.end = page_start;
+                    position.validity = Validity::TRUNCATED;
+                    break;
+                }
+            }
+        }
+
+        if !crc_is_valid {
+            let next_page: &[u8] = &data[page.end..std::cmp::min(data.len(), page.end + OGG_IDENTIFIER.len())];
+            if page.header_type & END_OF_STREAM == 0 && !OGG_IDENTIFIER.starts_with(next_page) {
+                // the page got cut off and something else follows
+                position.end = page_start;
+                position.validity = Validity::TRUNCATED;
+                break;
+            }
+            position.validity = Validity::CORRUPT;
+        }
+
+        if page.header_type & END_OF_STREAM != 0 {
+            open_streams.retain(|(serial, _)| *serial != page.serial);
+            if open_streams.len() == 0 {
+                position.end = page.end;
+            }
+        }
+
+        page_start = page.end;
+    }
+
+    return Some(position);
+}
+
+pub struct OggRipper;
+
+impl Ripper for OggRipper {
+    fn name(&self) -> &'static str {
+        return "Ogg";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::OGG;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::AUDIO;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&OGG_IDENTIFIER];
+    }
+
+    // every page starts with the signature, there's no need to look at them again
+    fn can_nest(&self) -> bool {
+        return false;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_ogg(data, start_index);
+    }
+}
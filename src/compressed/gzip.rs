@@ -0,0 +1,306 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+use crate::util::crc32::{crc32, crc32_update};
+use crate::util::inflate::{inflate, inflate_into, InflateError, InflatedLengths};
+
+// ID1, ID2 and deflate as the compression method
+const GZIP_IDENTIFIER: [u8; 3] = [0x1F, 0x8B, 0x08];
+// identifier, flags, modification time, extra flags and OS
+const GZIP_HEADER_LENGTH: usize = 10;
+// little-endian CRC-32 and size of decompressed data
+const GZIP_TRAILER_LENGTH: usize = 8;
+// file names longer than that are not real
+const MAX_NAME_LENGTH: usize = 1024;
+
+// header flags
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+const RESERVED_FLAGS: u8 = 0xE0;
+
+// What was found where a member header should be
+enum MemberHeader {
+    // where compressed data begins and the original file name, if there's one
+    VALID(usize, Option<String>),
+    // data ends before the header does
+    INCOMPLETE,
+    INVALID,
+}
+
+// Returns an index right after a zero-terminated field that starts at index
+fn skip_zero_terminated(data: &[u8], index: usize, max_length: usize) -> Result<usize, MemberHeader> {
+    let search_end: usize = std::cmp::min(data.len(), index.saturating_add(max_length));
+    match data[index.min(search_end)..search_end].iter().position(|byte| *byte == 0) {
+        Some(offset) => return Ok(index + offset + 1),
+        None if search_end == data.len() => return Err(MemberHeader::INCOMPLETE),
+        None => return Err(MemberHeader::INVALID),
+    }
+}
+
+fn read_member_header(data: &[u8], index: usize) -> MemberHeader {
+    if index + GZIP_IDENTIFIER.len() <= data.len() && data[index..index + GZIP_IDENTIFIER.len()] != GZIP_IDENTIFIER {
+        return MemberHeader::INVALID;
+    }
+    if index + GZIP_HEADER_LENGTH > data.len() {
+        return MemberHeader::INCOMPLETE;
+    }
+
+    let flags: u8 = data[index + 3];
+    let extra_flags: u8 = data[index + 8];
+    let os: u8 = data[index + 9];
+    if flags & RESERVED_FLAGS != 0 || !(extra_flags == 0 || extra_flags == 2 || extra_flags == 4) || (os > 13 && os != 255) {
+        return MemberHeader::INVALID;
+    }
+
+    let mut i: usize = index + GZIP_HEADER_LENGTH;
+    if flags & FEXTRA != 0 {
+        if i + 2 > data.len() {
+            return MemberHeader::INCOMPLETE;
+        }
+        i += 2 + u16::from_le_bytes([data[i], data[i + 1]]) as usize;
+    }
+
+    let mut name: Option<String> = None;
+    if flags & FNAME != 0 {
+        let name_start: usize = i;
+        match skip_zero_terminated(data, i, MAX_NAME_LENGTH) {
+            Ok(name_end) => i = name_end,
+            Err(error) => return error,
+        }
+        // names are in ISO 8859-1
+        name = Some(data[name_start..i - 1].iter().map(|byte| *byte as char).collect());
+    }
+    if flags & FCOMMENT != 0 {
+        match skip_zero_terminated(data, i, usize::MAX) {
+            Ok(comment_end) => i = comment_end,
+            Err(error) => return error,
+        }
+    }
+
+    if flags & FHCRC != 0 {
+        // lower half of CRC-32 of everything before it
+        if i + 2 > data.len() {
+            return MemberHeader::INCOMPLETE;
+        }
+        if u16::from_le_bytes([data[i], data[i + 1]]) != crc32(&data[index..i]) as u16 {
+            return MemberHeader::INVALID;
+        }
+        i += 2;
+    }
+
+    if i > data.len() {
+        return MemberHeader::INCOMPLETE;
+    }
+    return MemberHeader::VALID(i, name);
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    return u32::from_le_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]);
+}
+
+// Tries to read a gzip file that starts exactly at start_index, if a valid file was found -
+// returns positions of it including every member that follows right after the first one.
+// Members are decompressed to find their ends, though decompressed data is not kept around.
+// A file that decompresses to more than max_decompressed_size is cut where the limit was reached
+pub fn rip_gzip(data: &[u8], start_index: usize, max_decompressed_size: usize) -> Option<Position> {
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: ContentType::GZIP,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    let mut member_count: usize = 0;
+    let mut decompressed_size: usize = 0;
+    let mut member_start: usize = start_index;
+    loop {
+        let compressed_start: usize;
+        match read_member_header(data, member_start) {
+            MemberHeader::VALID(header_end, name) => {
+                compressed_start = header_end;
+                if let (0, Some(name)) = (member_count, name) {
+                    position.metadata.push(("name", name));
+                }
+            }
+            MemberHeader::INCOMPLETE if member_count == 0 => {
+                // no end in sight, it might be further than the data goes
+                position.end = data.len();
+                position.validity = Validity::TRUNCATED;
+                break;
+            }
+            _ if member_count == 0 => {
+                return None;
+            }
+            _ => {
+                // no more members
+                position.end = member_start;
+                break;
+            }
+        }
+
+        let mut checksum: u32 = 0;
+        let lengths: InflatedLengths;
+        let max_member_size: usize = max_decompressed_size - decompressed_size;
+        match inflate_into(&data[compressed_start..], max_member_size, &mut |bytes: &[u8]| checksum = crc32_update(checksum, bytes)) {
+            Ok(inflated_lengths) => {
+                lengths = inflated_lengths;
+            }
+            Err(InflateError::TRUNCATED) => {
+                position.end = data.len();
+                position.validity = Validity::TRUNCATED;
+                break;
+            }
+            Err(_) if member_count == 0 => {
+                return None;
+            }
+            Err(_) => {
+                // a header with no member behind it, the file ends before it
+                position.end = member_start;
+                break;
+            }
+        }
+
+        let trailer_start: usize = compressed_start + lengths.compressed_length;
+        if !lengths.is_complete {
+            // too big to go through, only the beginning is known to be fine
+            position.end = trailer_start;
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+        if trailer_start + GZIP_TRAILER_LENGTH > data.len() {
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+        if read_u32(data, trailer_start) != checksum || read_u32(data, trailer_start + 4) != lengths.decompressed_length as u32 {
+            position.validity = Validity::CORRUPT;
+        }
+
+        member_count += 1;
+        decompressed_size += lengths.decompressed_length;
+        member_start = trailer_start + GZIP_TRAILER_LENGTH;
+    }
+
+    if member_count > 0 {
+        position.metadata.push(("decompressed_size", decompressed_size.to_string()));
+    }
+    if member_count > 1 {
+        position.metadata.push(("members", member_count.to_string()));
+    }
+
+    return Some(position);
+}
+
+// Returns decompressed data of every member of a gzip file that takes up the whole content slice,
+// refusing to produce more than max_length bytes
+pub fn decompress_gzip(content: &[u8], max_length: usize) -> Option<Vec<u8>> {
+    let mut decompressed: Vec<u8> = Vec::new();
+    let mut member_start: usize = 0;
+    while member_start < content.len() {
+        let compressed_start: usize = match read_member_header(content, member_start) {
+            MemberHeader::VALID(header_end, _) => header_end,
+            _ => return None,
+        };
+
+        match inflate(&content[compressed_start..], max_length - decompressed.len()) {
+            Ok((member, compressed_length)) => {
+                decompressed.extend_from_slice(&member);
+                member_start = compressed_start + compressed_length + GZIP_TRAILER_LENGTH;
+            }
+            Err(_) => {
+                return None;
+            }
+        }
+    }
+
+    return Some(decompressed);
+}
+
+pub struct GzipRipper {
+    // files are not decompressed any further than that to find their ends
+    pub max_decompressed_size: usize,
+}
+
+impl Ripper for GzipRipper {
+    fn name(&self) -> &'static str {
+        return "gzip";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::GZIP;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::ARCHIVE;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&GZIP_IDENTIFIER];
+    }
+
+    // every member starts with the same header
+    fn can_nest(&self) -> bool {
+        return false;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_gzip(data, start_index, self.max_decompressed_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "hello hello hello gzip, " eight times
+    const MEMBER: [u8; 38] = [
+        0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57,
+        0xC8, 0x40, 0x22, 0xD3, 0xAB, 0x32, 0x0B, 0x74, 0x50, 0x44, 0x06, 0xB3, 0x38, 0x00, 0xD9, 0x20,
+        0x4D, 0x31, 0xC0, 0x00, 0x00, 0x00,
+    ];
+    const DECOMPRESSED_LENGTH: usize = 192;
+
+    #[test]
+    fn members_are_carved_together() {
+        let mut data: Vec<u8> = vec![0xAA; 5];
+        data.extend_from_slice(&MEMBER);
+        data.extend_from_slice(&MEMBER);
+        data.extend_from_slice(&[0xAA; 5]);
+
+        let position: Position = rip_gzip(&data, 5, 1000).expect("stream was not found");
+        assert_eq!(position.end, 5 + MEMBER.len() * 2);
+        assert_eq!(position.validity, Validity::VALID);
+        assert_eq!(decompress_gzip(&data[position.start..position.end], 1000), Some(b"hello hello hello gzip, ".repeat(16)));
+    }
+
+    #[test]
+    fn decompressing_stops_at_the_limit() {
+        let position: Position = rip_gzip(&MEMBER, 0, DECOMPRESSED_LENGTH / 2).expect("stream was not found");
+        assert!(position.end < MEMBER.len());
+        assert_eq!(position.validity, Validity::TRUNCATED);
+    }
+}
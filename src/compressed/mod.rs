@@ -0,0 +1,2 @@
+pub mod zlib;
+pub mod gzip;
\ No newline at end of file
@@ -0,0 +1,208 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+use crate::util::inflate::{inflate, inflate_into, InflateError, InflatedLengths};
+
+// CMF and FLG bytes of streams compressed with deflate (CM = 8) that use no preset dictionary,
+// one for each window size (CINFO = 0..7) and compression level
+static ZLIB_HEADERS: [&[u8]; 32] = [
+    &[0x08, 0x1D], &[0x08, 0x5B], &[0x08, 0x99], &[0x08, 0xD7], &[0x18, 0x19], &[0x18, 0x57], &[0x18, 0x95], &[0x18, 0xD3],
+    &[0x28, 0x15], &[0x28, 0x53], &[0x28, 0x91], &[0x28, 0xCF], &[0x38, 0x11], &[0x38, 0x4F], &[0x38, 0x8D], &[0x38, 0xCB],
+    &[0x48, 0x0D], &[0x48, 0x4B], &[0x48, 0x89], &[0x48, 0xC7], &[0x58, 0x09], &[0x58, 0x47], &[0x58, 0x85], &[0x58, 0xC3],
+    &[0x68, 0x05], &[0x68, 0x43], &[0x68, 0x81], &[0x68, 0xDE], &[0x78, 0x01], &[0x78, 0x5E], &[0x78, 0x9C], &[0x78, 0xDA],
+];
+const ZLIB_HEADER_LENGTH: usize = 2;
+// big-endian Adler-32 of decompressed data
+const ZLIB_TRAILER_LENGTH: usize = 4;
+const ADLER_MODULO: u32 = 65521;
+// how many bytes can be summed up before the sums have to be reduced
+const ADLER_BLOCK_LENGTH: usize = 5552;
+
+// Continues calculating Adler-32 over more data. Start with adler = 1
+fn adler32_update(adler: u32, data: &[u8]) -> u32 {
+    let mut a: u32 = adler & 0xFFFF;
+    let mut b: u32 = adler >> 16;
+    for block in data.chunks(ADLER_BLOCK_LENGTH) {
+        for byte in block {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= ADLER_MODULO;
+        b %= ADLER_MODULO;
+    }
+    return b << 16 | a;
+}
+
+fn is_zlib_header(data: &[u8], index: usize) -> bool {
+    return index + ZLIB_HEADER_LENGTH <= data.len() && ZLIB_HEADERS.contains(&&data[index..index + ZLIB_HEADER_LENGTH]);
+}
+
+// Tries to read a zlib stream that starts exactly at start_index, if a valid stream was found -
+// returns positions of it including the Adler-32 trailer. The stream is decompressed to find
+// its end, though decompressed data is not kept around. A two byte header says little, so
+// streams whose checksum doesn't match are not reported at all. Streams that decompress
+// to more than max_decompressed_size are cut where the limit was reached
+pub fn rip_zlib(data: &[u8], start_index: usize, max_decompressed_size: usize) -> Option<Position> {
+    if !is_zlib_header(data, start_index) {
+        return None;
+    }
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: ContentType::ZLIB,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    let mut checksum: u32 = 1;
+    let lengths: InflatedLengths;
+    match inflate_into(&data[start_index + ZLIB_HEADER_LENGTH..], max_decompressed_size, &mut |bytes: &[u8]| checksum = adler32_update(checksum, bytes)) {
+        Ok(inflated_lengths) => {
+            lengths = inflated_lengths;
+        }
+        Err(InflateError::TRUNCATED) => {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            return Some(position);
+        }
+        Err(_) => {
+            return None;
+        }
+    }
+
+    if lengths.decompressed_length == 0 {
+        // nothing to look into
+        return None;
+    }
+
+    let trailer_start: usize = start_index + ZLIB_HEADER_LENGTH + lengths.compressed_length;
+    if !lengths.is_complete {
+        // too big to go through, only the beginning is known to be fine
+        position.end = trailer_start;
+        position.validity = Validity::TRUNCATED;
+        return Some(position);
+    }
+
+    if trailer_start + ZLIB_TRAILER_LENGTH > data.len() {
+        position.end = data.len();
+        position.validity = Validity::TRUNCATED;
+        return Some(position);
+    }
+
+    let expected_checksum: u32 = u32::from_be_bytes([
+        data[trailer_start], data[trailer_start + 1], data[trailer_start + 2], data[trailer_start + 3]
+    ]);
+    if checksum != expected_checksum {
+        return None;
+    }
+
+    position.end = trailer_start + ZLIB_TRAILER_LENGTH;
+    position.metadata.push(("decompressed_size", lengths.decompressed_length.to_string()));
+
+    return Some(position);
+}
+
+// Returns decompressed data of a zlib stream that takes up the whole content slice,
+// refusing to produce more than max_length bytes
+pub fn decompress_zlib(content: &[u8], max_length: usize) -> Option<Vec<u8>> {
+    if !is_zlib_header(content, 0) {
+        return None;
+    }
+
+    match inflate(&content[ZLIB_HEADER_LENGTH..], max_length) {
+        Ok((decompressed, _)) => {
+            return Some(decompressed);
+        }
+        Err(_) => {
+            return None;
+        }
+    }
+}
+
+pub struct ZlibRipper {
+    // streams are not decompressed any further than that to find their ends
+    pub max_decompressed_size: usize,
+}
+
+impl Ripper for ZlibRipper {
+    fn name(&self) -> &'static str {
+        return "zlib";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::ZLIB;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::ARCHIVE;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &ZLIB_HEADERS;
+    }
+
+    // header bytes inside of compressed data are just a coincidence
+    fn can_nest(&self) -> bool {
+        return false;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_zlib(data, start_index, self.max_decompressed_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "hello hello hello zlib, " eight times
+    const STREAM: [u8; 26] = [
+        0x78, 0x9C, 0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x22, 0xAB, 0x72, 0x32, 0x93, 0x74,
+        0x50, 0x44, 0x06, 0xB3, 0x38, 0x00, 0x18, 0xBE, 0x44, 0xC9,
+    ];
+    const DECOMPRESSED_LENGTH: usize = 192;
+
+    #[test]
+    fn stream_is_carved_up_to_its_checksum() {
+        let mut data: Vec<u8> = STREAM.to_vec();
+        data.extend_from_slice(&[0xAA; 5]);
+
+        let position: Position = rip_zlib(&data, 0, 1000).expect("stream was not found");
+        assert_eq!(position.end, STREAM.len());
+        assert_eq!(position.validity, Validity::VALID);
+
+        // the checksum is all there is to tell a stream from chance
+        data[STREAM.len() - 1] ^= 0xFF;
+        assert!(rip_zlib(&data, 0, 1000).is_none());
+    }
+
+    #[test]
+    fn decompressing_stops_at_the_limit() {
+        let position: Position = rip_zlib(&STREAM, 0, DECOMPRESSED_LENGTH / 2).expect("stream was not found");
+        assert!(position.end < STREAM.len());
+        assert_eq!(position.validity, Validity::TRUNCATED);
+    }
+}
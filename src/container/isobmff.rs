@@ -0,0 +1,243 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+
+const FTYP: [u8; 4] = *b"ftyp";
+// box size comes before the type
+const FTYP_OFFSET: usize = 4;
+// size, type, major brand and minor version
+const MIN_FTYP_LENGTH: usize = 16;
+const MAX_FTYP_LENGTH: usize = 4096;
+const BOX_HEADER_LENGTH: usize = 8;
+const LARGE_BOX_HEADER_LENGTH: usize = 16;
+
+// boxes that hold what's needed to actually play or show the content
+const MOOV: [u8; 4] = *b"moov";
+const META: [u8; 4] = *b"meta";
+
+// Brands that tell what's inside and what that content is.
+// Generic ones go last so compatible brands get a chance to be more specific
+const BRANDS: [(&[u8; 4], ContentType, RipType); 21] = [
+    (b"M4A ", ContentType::M4A, RipType::AUDIO),
+    (b"M4B ", ContentType::M4A, RipType::AUDIO),
+    (b"M4P ", ContentType::M4A, RipType::AUDIO),
+    (b"F4A ", ContentType::M4A, RipType::AUDIO),
+    (b"qt  ", ContentType::MOV, RipType::VIDEO),
+    (b"3gp4", ContentType::THREEGP, RipType::VIDEO),
+    (b"3gp5", ContentType::THREEGP, RipType::VIDEO),
+    (b"3gp6", ContentType::THREEGP, RipType::VIDEO),
+    (b"3gp7", ContentType::THREEGP, RipType::VIDEO),
+    (b"3g2a", ContentType::THREEG2, RipType::VIDEO),
+    (b"avif", ContentType::AVIF, RipType::IMG),
+    (b"avis", ContentType::AVIF, RipType::IMG),
+    (b"heic", ContentType::HEIC, RipType::IMG),
+    (b"heix", ContentType::HEIC, RipType::IMG),
+    (b"hevc", ContentType::HEIC, RipType::IMG),
+    (b"heim", ContentType::HEIC, RipType::IMG),
+    (b"heis", ContentType::HEIC, RipType::IMG),
+    (b"mif1", ContentType::HEIF, RipType::IMG),
+    (b"msf1", ContentType::HEIF, RipType::IMG),
+    (b"M4V ", ContentType::MP4, RipType::VIDEO),
+    (b"isom", ContentType::MP4, RipType::VIDEO),
+];
+
+// Box types are made of printable ASCII
+fn is_valid_box_type(box_type: &[u8]) -> bool {
+    return box_type.iter().all(|byte| (0x20..=0x7E).contains(byte));
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    return u32::from_be_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]);
+}
+
+// Picks content type by the brands in ftyp, unknown brands are taken for mp4
+fn identify_brand(ftyp: &[u8]) -> (ContentType, RipType) {
+    // major brand, minor version, then compatible brands
+    let mut brands: Vec<&[u8]> = vec![&ftyp[8..12]];
+    brands.extend(ftyp[16..].chunks_exact(4));
+
+    for (brand, content_type, rip_type) in BRANDS.iter() {
+        if brands.iter().any(|b| b == brand) {
+            return (*content_type, *rip_type);
+        }
+    }
+
+    return (ContentType::MP4, RipType::VIDEO);
+}
+
+// Tries to read an ISO Base Media file (mp4, mov, m4a, 3gp, heic, avif...) that starts
+// exactly at start_index with an ftyp box, if one was found - returns positions
+// of the file by summing up its top-level boxes until the first invalid one.
+// A file without a moov or meta box is missing its description and is marked as truncated
+pub fn rip_isobmff(data: &[u8], start_index: usize) -> Option<Position> {
+    return rip_isobmff_of_type(data, start_index).map(|(position, _)| position);
+}
+
+// Same as rip_isobmff, but also tells what kind of content the brands say it is
+fn rip_isobmff_of_type(data: &[u8], start_index: usize) -> Option<(Position, RipType)> {
+    if start_index + MIN_FTYP_LENGTH > data.len() {
+        return None;
+    }
+
+    if data[start_index + FTYP_OFFSET..start_index + FTYP_OFFSET + FTYP.len()] != FTYP {
+        return None;
+    }
+
+    let ftyp_length: usize = read_u32(data, start_index) as usize;
+    if !(MIN_FTYP_LENGTH..=MAX_FTYP_LENGTH).contains(&ftyp_length) || !(ftyp_length - MIN_FTYP_LENGTH).is_multiple_of(4) {
+        return None;
+    }
+    if start_index + ftyp_length > data.len() {
+        return None;
+    }
+
+    let ftyp: &[u8] = &data[start_index..start_index + ftyp_length];
+    if !is_valid_box_type(&ftyp[8..12]) {
+        return None;
+    }
+
+    let (content_type, rip_type): (ContentType, RipType) = identify_brand(ftyp);
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: content_type,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: vec![("brand", String::from_utf8_lossy(&ftyp[8..12]).trim_end().to_string())],
+    };
+
+    let mut has_description: bool = false;
+    let mut box_start: usize = start_index + ftyp_length;
+    while position.end == usize::MAX {
+        if box_start + BOX_HEADER_LENGTH > data.len() {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        let box_type: &[u8] = &data[box_start + 4..box_start + 8];
+        if !is_valid_box_type(box_type) || box_type == FTYP {
+            // this is not a box of this file anymore, the file ends here
+            position.end = box_start;
+            break;
+        }
+
+        let box_length: usize;
+        match read_u32(data, box_start) {
+            0 => {
+                // the box goes on to the end of the file
+                position.end = data.len();
+                if box_type == MOOV || box_type == META {
+                    has_description = true;
+                }
+                break;
+            }
+            1 => {
+                if box_start + LARGE_BOX_HEADER_LENGTH > data.len() {
+                    position.end = data.len();
+                    position.validity = Validity::TRUNCATED;
+                    break;
+                }
+                let large_size: u64 = u64::from_be_bytes([
+                    data[box_start + 8], data[box_start + 9], data[box_start + 10], data[box_start + 11],
+                    data[box_start + 12], data[box_start + 13], data[box_start + 14], data[box_start + 15],
+                ]);
+                if large_size < LARGE_BOX_HEADER_LENGTH as u64 || large_size > usize::MAX as u64 {
+                    position.end = box_start;
+                    break;
+                }
+                box_length = large_size as usize;
+            }
+            size if (size as usize) < BOX_HEADER_LENGTH => {
+                position.end = box_start;
+                break;
+            }
+            size => {
+                box_length = size as usize;
+            }
+        }
+
+        if box_type == MOOV || box_type == META {
+            has_description = true;
+        }
+
+        match box_start.checked_add(box_length) {
+            Some(box_end) if box_end <= data.len() => {
+                box_start = box_end;
+            }
+            _ => {
+                position.end = data.len();
+                position.validity = Validity::TRUNCATED;
+            }
+        }
+    }
+
+    if !has_description && position.validity == Validity::VALID {
+        position.validity = Validity::TRUNCATED;
+    }
+
+    return Some((position, rip_type));
+}
+
+// Rips ISO Base Media files whose brands say they are of rip_type
+pub struct IsoBmffRipper {
+    pub rip_type: RipType,
+}
+
+impl Ripper for IsoBmffRipper {
+    fn name(&self) -> &'static str {
+        match self.rip_type {
+            RipType::IMG => return "HEIC/HEIF/AVIF",
+            RipType::AUDIO => return "M4A",
+            _ => return "MP4/MOV/3GP",
+        }
+    }
+
+    fn content_type(&self) -> ContentType {
+        match self.rip_type {
+            RipType::IMG => return ContentType::HEIC,
+            RipType::AUDIO => return ContentType::M4A,
+            _ => return ContentType::MP4,
+        }
+    }
+
+    fn rip_type(&self) -> RipType {
+        return self.rip_type;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&FTYP];
+    }
+
+    fn magic_offset(&self) -> usize {
+        return FTYP_OFFSET;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        match rip_isobmff_of_type(data, start_index) {
+            Some((position, rip_type)) if rip_type == self.rip_type => return Some(position),
+            _ => return None,
+        }
+    }
+}
@@ -0,0 +1,303 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+
+const EBML_IDENTIFIER: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+const MAX_EBML_HEADER_LENGTH: u64 = 1024;
+// 4 bytes of id and 8 bytes of size at most
+const MAX_ELEMENT_HEADER_LENGTH: usize = 12;
+
+// element ids, with their length markers
+const EBML: u32 = 0x1A45DFA3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x18538067;
+const CLUSTER: u32 = 0x1F43B675;
+const VOID: u32 = 0xEC;
+const CRC32: u32 = 0xBF;
+
+// elements that can be found right inside of a segment
+const SEGMENT_CHILDREN: [u32; 10] = [
+    // SeekHead, Info, Tracks, Cluster, Cues, Attachments, Chapters, Tags
+    0x114D9B74, 0x1549A966, 0x1654AE6B, CLUSTER, 0x1C53BB6B, 0x1941A469, 0x1043A770, 0x1254C367,
+    VOID, CRC32,
+];
+
+// elements that can be found right inside of a cluster
+const CLUSTER_CHILDREN: [u32; 9] = [
+    // Timestamp, SilentTracks, Position, PrevSize, SimpleBlock, BlockGroup, EncryptedBlock
+    0xE7, 0x5854, 0xA7, 0xAB, 0xA3, 0xA0, 0xAF,
+    VOID, CRC32,
+];
+
+// Reads an element id at index, returns it with its length marker and how many bytes it takes
+fn read_element_id(data: &[u8], index: usize) -> Option<(u32, usize)> {
+    if index >= data.len() || data[index] < 0x10 {
+        // ids are 4 bytes at most
+        return None;
+    }
+
+    let length: usize = data[index].leading_zeros() as usize + 1;
+    if index + length > data.len() {
+        return None;
+    }
+
+    let mut id: u32 = 0;
+    for byte in &data[index..index + length] {
+        id = (id << 8) | *byte as u32;
+    }
+    return Some((id, length));
+}
+
+// Reads an element data size at index, returns it (None if unknown) and how many bytes it takes
+fn read_element_size(data: &[u8], index: usize) -> Option<(Option<u64>, usize)> {
+    if index >= data.len() || data[index] == 0 {
+        return None;
+    }
+
+    let length: usize = data[index].leading_zeros() as usize + 1;
+    if index + length > data.len() {
+        return None;
+    }
+
+    let mut size: u64 = (data[index] as u64) & (0xFF >> length);
+    let mut is_unknown: bool = size == (0xFF >> length);
+    for byte in &data[index + 1..index + length] {
+        size = (size << 8) | *byte as u64;
+        is_unknown = is_unknown && *byte == 0xFF;
+    }
+
+    if is_unknown {
+        return Some((None, length));
+    }
+    return Some((Some(size), length));
+}
+
+// Header of an element: its id, data size if known and where its data starts
+struct Element {
+    id: u32,
+    size: Option<u64>,
+    data_start: usize,
+}
+
+fn read_element(data: &[u8], index: usize) -> Option<Element> {
+    let (id, id_length): (u32, usize) = read_element_id(data, index)?;
+    let (size, size_length): (Option<u64>, usize) = read_element_size(data, index + id_length)?;
+    return Some(Element{
+        id: id,
+        size: size,
+        data_start: index + id_length + size_length,
+    });
+}
+
+// Returns the index right after an element of known size, if it fits into usize
+fn element_end(element: &Element, size: u64) -> Option<usize> {
+    return element.data_start.checked_add(usize::try_from(size).ok()?);
+}
+
+// Goes over elements starting at index for as long as they are one of allowed ids,
+// following unknown-size clusters into their children.
+// Returns the index of the first element that is not allowed
+// or None if data ends before elements do
+fn walk_elements(data: &[u8], index: usize, allowed_ids: &[u32]) -> Option<usize> {
+    let mut i: usize = index;
+    loop {
+        let element: Element;
+        match read_element(data, i) {
+            Some(e) => {
+                element = e;
+            }
+            None => {
+                if i + MAX_ELEMENT_HEADER_LENGTH > data.len() {
+                    // the header might be cut off by the end of data
+                    return None;
+                }
+                return Some(i);
+            }
+        }
+
+        if !allowed_ids.contains(&element.id) {
+            return Some(i);
+        }
+
+        match element.size {
+            Some(size) => {
+                i = element_end(&element, size)?;
+                if i > data.len() {
+                    return None;
+                }
+            }
+            None => {
+                if element.id != CLUSTER {
+                    // there's no telling where anything else of unknown size ends
+                    return Some(i);
+                }
+                // a live cluster ends where something that can't be inside of it begins
+                i = walk_elements(data, element.data_start, &CLUSTER_CHILDREN)?;
+            }
+        }
+    }
+}
+
+// Finds DocType among EBML header children
+fn read_doc_type(data: &[u8], header: &Element, header_end: usize) -> Option<String> {
+    let mut i: usize = header.data_start;
+    while i < header_end {
+        let element: Element = read_element(data, i)?;
+        let end: usize = element_end(&element, element.size?)?;
+        if end > header_end {
+            return None;
+        }
+
+        if element.id == DOC_TYPE {
+            let doc_type: &[u8] = &data[element.data_start..end];
+            // strings may be padded with zeros
+            let doc_type: &[u8] = match doc_type.iter().position(|byte| *byte == 0) {
+                Some(zero_index) => &doc_type[..zero_index],
+                None => doc_type,
+            };
+            return Some(String::from_utf8_lossy(doc_type).to_string());
+        }
+        i = end;
+    }
+
+    return None;
+}
+
+// Tries to read a Matroska or WebM file that starts exactly at start_index with an EBML header,
+// if one was found - returns positions of the file up to the end of its segment.
+// Segments of unknown size are walked element by element, clusters of unknown size - block by block
+pub fn rip_matroska(data: &[u8], start_index: usize) -> Option<Position> {
+    if start_index + EBML_IDENTIFIER.len() > data.len() {
+        return None;
+    }
+
+    if data[start_index..start_index + EBML_IDENTIFIER.len()] != EBML_IDENTIFIER {
+        return None;
+    }
+
+    let header: Element = read_element(data, start_index)?;
+    if header.id != EBML {
+        return None;
+    }
+    let header_size: u64 = header.size?;
+    if header_size > MAX_EBML_HEADER_LENGTH {
+        return None;
+    }
+    let header_end: usize = header.data_start + header_size as usize;
+    if header_end > data.len() {
+        return None;
+    }
+
+    let content_type: ContentType;
+    let doc_type: String = read_doc_type(data, &header, header_end)?;
+    match doc_type.as_str() {
+        "matroska" => content_type = ContentType::MKV,
+        "webm" => content_type = ContentType::WEBM,
+        _ => return None,
+    }
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: content_type,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: vec![("doc_type", doc_type)],
+    };
+
+    let segment: Element;
+    match read_element(data, header_end) {
+        Some(element) if element.id == SEGMENT => {
+            segment = element;
+        }
+        Some(_) => {
+            // a header without anything it describes
+            return None;
+        }
+        None => {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            return Some(position);
+        }
+    }
+
+    // walk the segment even if its size is known, to make sure it's all there
+    let walked_end: usize;
+    match walk_elements(data, segment.data_start, &SEGMENT_CHILDREN) {
+        Some(end) if end > segment.data_start => {
+            walked_end = end;
+        }
+        Some(_) => {
+            // something has to be inside of a segment
+            return None;
+        }
+        None => {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            return Some(position);
+        }
+    }
+
+    position.end = walked_end;
+    if let Some(size) = segment.size {
+        match element_end(&segment, size) {
+            Some(segment_end) if segment_end == walked_end => {}
+            Some(segment_end) if segment_end > walked_end => {
+                // the segment breaks off into something else
+                position.validity = Validity::TRUNCATED;
+            }
+            _ => {
+                // elements go beyond the segment
+                position.validity = Validity::CORRUPT;
+            }
+        }
+    }
+
+    return Some(position);
+}
+
+pub struct MatroskaRipper;
+
+impl Ripper for MatroskaRipper {
+    fn name(&self) -> &'static str {
+        return "MKV/WebM";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::MKV;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::VIDEO;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&EBML_IDENTIFIER];
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_matroska(data, start_index);
+    }
+}
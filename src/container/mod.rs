@@ -0,0 +1,4 @@
+pub mod riff;
+pub mod isobmff;
+pub mod matroska;
+pub mod zip;
\ No newline at end of file
@@ -0,0 +1,206 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+
+const RIFF_IDENTIFIER: [u8; 4] = *b"RIFF";
+// "RIFF", size and form type
+const RIFF_HEADER_LENGTH: usize = 12;
+// chunk id and size
+const CHUNK_HEADER_LENGTH: usize = 8;
+const FMT: [u8; 4] = *b"fmt ";
+
+// Form types that are carved, what content they hold and what that content is called
+static RIFF_FORMS: [([u8; 4], ContentType, RipType, &str); 4] = [
+    (*b"WAVE", ContentType::WAV, RipType::AUDIO, "WAV"),
+    (*b"WEBP", ContentType::WEBP, RipType::IMG, "WebP"),
+    (*b"AVI ", ContentType::AVI, RipType::VIDEO, "AVI"),
+    (*b"ACON", ContentType::ANI, RipType::IMG, "ANI"),
+];
+
+// Chunk ids and form types are made of printable ASCII
+fn is_valid_fourcc(fourcc: &[u8]) -> bool {
+    return fourcc.iter().all(|byte| (0x20..=0x7E).contains(byte));
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    return u32::from_le_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]);
+}
+
+// Puts channels, sampling rate and sample size from a WAVE fmt chunk into metadata
+fn push_wave_format(position: &mut Position, fmt: &[u8]) {
+    if fmt.len() < 16 {
+        return;
+    }
+
+    position.metadata.push(("channels", u16::from_le_bytes([fmt[2], fmt[3]]).to_string()));
+    position.metadata.push(("sampling_rate", read_u32(fmt, 4).to_string()));
+    position.metadata.push(("bits_per_sample", u16::from_le_bytes([fmt[14], fmt[15]]).to_string()));
+}
+
+// Tries to read a RIFF file of a known form type that starts exactly at start_index,
+// if valid RIFF bytes were found - returns positions of a file by its declared size.
+// Chunks are walked to make sure the declared size can be trusted: a file whose chunks
+// break off into something else is cut there and marked as truncated, one whose chunks
+// disagree with the declared size is marked as corrupt
+pub fn rip_riff(data: &[u8], start_index: usize) -> Option<Position> {
+    if start_index + RIFF_HEADER_LENGTH > data.len() {
+        return None;
+    }
+
+    if data[start_index..start_index + RIFF_IDENTIFIER.len()] != RIFF_IDENTIFIER {
+        return None;
+    }
+
+    let riff_size: usize = read_u32(data, start_index + 4) as usize;
+    let form_type: &[u8] = &data[start_index + 8..start_index + RIFF_HEADER_LENGTH];
+    let content_type: ContentType;
+    match RIFF_FORMS.iter().find(|form| form.0 == form_type) {
+        Some(form) => {
+            content_type = form.1;
+        }
+        None => {
+            return None;
+        }
+    }
+
+    // there has to be room for at least one chunk
+    if riff_size < 4 + CHUNK_HEADER_LENGTH {
+        return None;
+    }
+
+    let riff_end: usize = start_index + CHUNK_HEADER_LENGTH + riff_size;
+    let mut position: Position = Position{
+        start: start_index,
+        end: riff_end,
+        content_type: content_type,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    let mut chunk_count: usize = 0;
+    let mut chunk_start: usize = start_index + RIFF_HEADER_LENGTH;
+    while chunk_start < riff_end {
+        if chunk_start + CHUNK_HEADER_LENGTH > data.len() {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        let chunk_id: &[u8] = &data[chunk_start..chunk_start + 4];
+        let chunk_size: usize = read_u32(data, chunk_start + 4) as usize;
+        if !is_valid_fourcc(chunk_id) {
+            if chunk_count == 0 {
+                return None;
+            }
+            // this is not a chunk anymore, the file ends prematurely
+            position.end = chunk_start;
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+        chunk_count += 1;
+
+        let chunk_data_start: usize = chunk_start + CHUNK_HEADER_LENGTH;
+        if content_type == ContentType::WAV && chunk_id == FMT && chunk_data_start + chunk_size <= data.len() {
+            push_wave_format(&mut position, &data[chunk_data_start..chunk_data_start + chunk_size]);
+        }
+
+        // chunks are padded to an even size, though the last one may come without padding
+        let chunk_end: usize = chunk_data_start + chunk_size + (chunk_size & 1);
+        if chunk_end > riff_end {
+            if chunk_end - (chunk_size & 1) != riff_end {
+                // the chunk says it goes beyond the file
+                position.validity = Validity::CORRUPT;
+            }
+            break;
+        }
+        if chunk_end > data.len() {
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        chunk_start = chunk_end;
+    }
+
+    if position.end > data.len() {
+        position.end = data.len();
+        position.validity = Validity::TRUNCATED;
+    }
+
+    return Some(position);
+}
+
+// Rips RIFF files of every form type that holds content of rip_type, or of every known form type
+// if it is ALL. All of them start the same way, so one ripper reads each RIFF header once
+pub struct RiffRipper {
+    pub rip_type: RipType,
+}
+
+impl RiffRipper {
+    // Whether any form type holds content of rip_type
+    pub fn carves(rip_type: RipType) -> bool {
+        return rip_type == RipType::ALL || RIFF_FORMS.iter().any(|form| form.2 == rip_type);
+    }
+
+    fn is_wanted(&self, content_type: ContentType) -> bool {
+        return self.rip_type == RipType::ALL
+            || RIFF_FORMS.iter().any(|form| form.1 == content_type && form.2 == self.rip_type);
+    }
+}
+
+impl Ripper for RiffRipper {
+    fn name(&self) -> &'static str {
+        match self.rip_type {
+            RipType::IMG => return "WebP/ANI",
+            RipType::AUDIO => return "WAV",
+            RipType::VIDEO => return "AVI",
+            _ => return "WAV/WebP/AVI/ANI",
+        }
+    }
+
+    // found positions tell the actual type of their content
+    fn content_type(&self) -> ContentType {
+        match self.rip_type {
+            RipType::IMG => return ContentType::WEBP,
+            RipType::VIDEO => return ContentType::AVI,
+            _ => return ContentType::WAV,
+        }
+    }
+
+    fn rip_type(&self) -> RipType {
+        return self.rip_type;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&RIFF_IDENTIFIER];
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        match rip_riff(data, start_index) {
+            Some(position) if self.is_wanted(position.content_type) => return Some(position),
+            _ => return None,
+        }
+    }
+}
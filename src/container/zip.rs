@@ -0,0 +1,789 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::path::PathBuf;
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+use crate::util::crc32::crc32;
+use crate::util::inflate::{inflate, InflateError};
+
+// record signatures and lengths of their fixed parts
+const LOCAL_FILE_HEADER: [u8; 4] = *b"PK\x03\x04";
+const LOCAL_FILE_HEADER_LENGTH: usize = 30;
+const CENTRAL_DIRECTORY_HEADER: [u8; 4] = *b"PK\x01\x02";
+const CENTRAL_DIRECTORY_HEADER_LENGTH: usize = 46;
+const END_OF_CENTRAL_DIRECTORY: [u8; 4] = *b"PK\x05\x06";
+const END_OF_CENTRAL_DIRECTORY_LENGTH: usize = 22;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: [u8; 4] = *b"PK\x06\x06";
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LENGTH: usize = 56;
+const ZIP64_LOCATOR: [u8; 4] = *b"PK\x06\x07";
+const ZIP64_LOCATOR_LENGTH: usize = 20;
+const DATA_DESCRIPTOR: [u8; 4] = *b"PK\x07\x08";
+
+const MAX_COMMENT_LENGTH: usize = 0xFFFF;
+// longer names are possible, but no real archive has them
+const MAX_NAME_LENGTH: usize = 4096;
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+
+// general purpose flags
+const ENCRYPTED: u16 = 1 << 0;
+const HAS_DATA_DESCRIPTOR: u16 = 1 << 3;
+
+// compression methods
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+const KNOWN_METHODS: [u16; 19] = [0, 1, 2, 3, 4, 5, 6, 8, 9, 10, 12, 14, 19, 93, 94, 95, 96, 97, 98];
+// AES encrypted members say so instead of naming the actual method
+const AES_ENCRYPTED: u16 = 99;
+
+fn read_u16(data: &[u8], index: usize) -> u16 {
+    return u16::from_le_bytes([data[index], data[index + 1]]);
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    return u32::from_le_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]);
+}
+
+fn read_u64(data: &[u8], index: usize) -> u64 {
+    return read_u32(data, index) as u64 | (read_u32(data, index + 4) as u64) << 32;
+}
+
+fn has_signature(data: &[u8], index: usize, signature: &[u8; 4]) -> bool {
+    return index + signature.len() <= data.len() && data[index..index + signature.len()] == *signature;
+}
+
+// Returns an index of the first signature occurrence at or after from_index
+fn find_signature(data: &[u8], from_index: usize, signature: &[u8; 4]) -> Option<usize> {
+    if from_index >= data.len() {
+        return None;
+    }
+
+    return data[from_index..]
+        .windows(signature.len())
+        .position(|window| window == signature)
+        .map(|offset| from_index + offset);
+}
+
+// A file or a directory stored in an archive, as the central directory describes it
+#[derive(Debug, Clone)]
+pub struct ZipMember {
+    pub name: String,
+    pub flags: u16,
+    pub method: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    // where the member's local header is in the archive
+    pub local_header_index: usize,
+}
+
+impl ZipMember {
+    pub fn is_directory(&self) -> bool {
+        return self.name.ends_with('/') || self.name.ends_with('\\');
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        return self.flags & ENCRYPTED != 0 || self.method == AES_ENCRYPTED;
+    }
+
+    pub fn method_name(&self) -> &'static str {
+        match self.method {
+            STORED => return "stored",
+            DEFLATED => return "deflated",
+            9 => return "deflate64",
+            12 => return "bzip2",
+            14 => return "lzma",
+            93 => return "zstd",
+            95 => return "xz",
+            98 => return "ppmd",
+            AES_ENCRYPTED => return "aes",
+            _ => return "unknown",
+        }
+    }
+
+    // Returns the member's name as a relative path, or None if the name
+    // tries to get out of the directory it's extracted to
+    pub fn relative_path(&self) -> Option<PathBuf> {
+        let mut path: PathBuf = PathBuf::new();
+        for component in self.name.split(['/', '\\']) {
+            match component {
+                "" | "." => continue,
+                ".." => return None,
+                // drive letters and alternate streams
+                _ if component.contains(':') => return None,
+                _ => path.push(component),
+            }
+        }
+
+        if path.as_os_str().is_empty() {
+            return None;
+        }
+        return Some(path);
+    }
+}
+
+// Why a member could not be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberError {
+    // encrypted or compressed with something other than deflate
+    UNSUPPORTED,
+    // member data is missing or can't be decompressed
+    MALFORMED,
+    // member is bigger than allowed
+    OVERSIZED,
+    // decompressed data does not match its size or CRC-32
+    CORRUPT,
+}
+
+impl MemberError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemberError::UNSUPPORTED => return "unsupported compression or encryption",
+            MemberError::MALFORMED => return "malformed member data",
+            MemberError::OVERSIZED => return "member is too big",
+            MemberError::CORRUPT => return "size or CRC-32 mismatch",
+        }
+    }
+}
+
+struct CentralDirectory {
+    members: Vec<ZipMember>,
+    // index right after the archive comment
+    end: usize,
+    is_zip64: bool,
+}
+
+// Replaces sizes and offset that did not fit into 32 bits with their values from the ZIP64 extra field
+fn apply_zip64_extra(extra: &[u8], uncompressed_size: &mut u64, compressed_size: &mut u64, local_header_offset: &mut u64) {
+    let mut i: usize = 0;
+    while i + 4 <= extra.len() {
+        let id: u16 = read_u16(extra, i);
+        let size: usize = read_u16(extra, i + 2) as usize;
+        if i + 4 + size > extra.len() {
+            return;
+        }
+
+        if id == ZIP64_EXTRA_FIELD {
+            // only the fields that overflowed are present, in this order
+            let field: &[u8] = &extra[i + 4..i + 4 + size];
+            let mut field_index: usize = 0;
+            for value in [uncompressed_size, compressed_size, local_header_offset] {
+                if *value != 0xFFFFFFFF {
+                    continue;
+                }
+                if field_index + 8 > field.len() {
+                    return;
+                }
+                *value = read_u64(field, field_index);
+                field_index += 8;
+            }
+            return;
+        }
+
+        i += 4 + size;
+    }
+}
+
+// Reads the central directory that the end of central directory record at eocd_index describes,
+// including the ZIP64 records right before it. Local header indices are worked out from where
+// the central directory is, so archives that got embedded into something are read just fine
+fn read_central_directory(data: &[u8], eocd_index: usize) -> Option<CentralDirectory> {
+    if !has_signature(data, eocd_index, &END_OF_CENTRAL_DIRECTORY) || eocd_index + END_OF_CENTRAL_DIRECTORY_LENGTH > data.len() {
+        return None;
+    }
+
+    let disk_entry_count: u64 = read_u16(data, eocd_index + 8) as u64;
+    let mut entry_count: u64 = read_u16(data, eocd_index + 10) as u64;
+    let mut directory_size: u64 = read_u32(data, eocd_index + 12) as u64;
+    let mut directory_offset: u64 = read_u32(data, eocd_index + 16) as u64;
+    let comment_length: usize = read_u16(data, eocd_index + 20) as usize;
+    let mut directory_end: usize = eocd_index;
+    let mut is_zip64: bool = false;
+
+    if eocd_index >= ZIP64_LOCATOR_LENGTH && has_signature(data, eocd_index - ZIP64_LOCATOR_LENGTH, &ZIP64_LOCATOR) {
+        // the ZIP64 record is expected right before its locator, without any extensible data
+        if eocd_index < ZIP64_LOCATOR_LENGTH + ZIP64_END_OF_CENTRAL_DIRECTORY_LENGTH {
+            return None;
+        }
+        let record_index: usize = eocd_index - ZIP64_LOCATOR_LENGTH - ZIP64_END_OF_CENTRAL_DIRECTORY_LENGTH;
+        if !has_signature(data, record_index, &ZIP64_END_OF_CENTRAL_DIRECTORY)
+            || read_u64(data, record_index + 4) != (ZIP64_END_OF_CENTRAL_DIRECTORY_LENGTH - 12) as u64 {
+            return None;
+        }
+
+        if read_u64(data, record_index + 24) != read_u64(data, record_index + 32) {
+            // spans several disks
+            return None;
+        }
+        entry_count = read_u64(data, record_index + 32);
+        directory_size = read_u64(data, record_index + 40);
+        directory_offset = read_u64(data, record_index + 48);
+        directory_end = record_index;
+        is_zip64 = true;
+    } else if disk_entry_count != entry_count {
+        // spans several disks
+        return None;
+    } else if entry_count == 0xFFFF || directory_size == 0xFFFFFFFF || directory_offset == 0xFFFFFFFF {
+        // values overflowed, but there's no ZIP64 record to hold them
+        return None;
+    }
+
+    if directory_size > directory_end as u64 || directory_offset > i64::MAX as u64 {
+        return None;
+    }
+    let directory_start: usize = directory_end - directory_size as usize;
+    // every entry takes its fixed part at least
+    if entry_count > directory_size / CENTRAL_DIRECTORY_HEADER_LENGTH as u64 {
+        return None;
+    }
+    // where the archive would start for its offsets to make sense
+    let archive_start: i64 = directory_start as i64 - directory_offset as i64;
+
+    let mut members: Vec<ZipMember> = Vec::with_capacity(entry_count as usize);
+    let mut i: usize = directory_start;
+    for _ in 0..entry_count {
+        if i + CENTRAL_DIRECTORY_HEADER_LENGTH > directory_end || !has_signature(data, i, &CENTRAL_DIRECTORY_HEADER) {
+            return None;
+        }
+
+        let name_length: usize = read_u16(data, i + 28) as usize;
+        let extra_length: usize = read_u16(data, i + 30) as usize;
+        let comment_length: usize = read_u16(data, i + 32) as usize;
+        let entry_end: usize = i + CENTRAL_DIRECTORY_HEADER_LENGTH + name_length + extra_length + comment_length;
+        if entry_end > directory_end {
+            return None;
+        }
+
+        let name_start: usize = i + CENTRAL_DIRECTORY_HEADER_LENGTH;
+        let extra_start: usize = name_start + name_length;
+        let mut uncompressed_size: u64 = read_u32(data, i + 24) as u64;
+        let mut compressed_size: u64 = read_u32(data, i + 20) as u64;
+        let mut local_header_offset: u64 = read_u32(data, i + 42) as u64;
+        apply_zip64_extra(
+            &data[extra_start..extra_start + extra_length],
+            &mut uncompressed_size,
+            &mut compressed_size,
+            &mut local_header_offset,
+        );
+
+        if local_header_offset > i64::MAX as u64 {
+            return None;
+        }
+        let local_header_index: i64;
+        match archive_start.checked_add(local_header_offset as i64) {
+            Some(index) => {
+                local_header_index = index;
+            }
+            None => {
+                // points further than anything could be, leave the entry out
+                i = entry_end;
+                continue;
+            }
+        }
+        if local_header_index < 0 {
+            return None;
+        }
+
+        members.push(ZipMember{
+            name: String::from_utf8_lossy(&data[name_start..extra_start]).into_owned(),
+            flags: read_u16(data, i + 8),
+            method: read_u16(data, i + 10),
+            crc32: read_u32(data, i + 16),
+            compressed_size: compressed_size,
+            uncompressed_size: uncompressed_size,
+            local_header_index: local_header_index as usize,
+        });
+
+        i = entry_end;
+    }
+
+    if i != directory_end {
+        // entries don't fill the directory
+        return None;
+    }
+
+    return Some(CentralDirectory{
+        members: members,
+        end: eocd_index + END_OF_CENTRAL_DIRECTORY_LENGTH + comment_length,
+        is_zip64: is_zip64,
+    });
+}
+
+// Checks that a local file header at index looks like one, returns where its data begins
+fn check_local_header(data: &[u8], index: usize) -> Option<usize> {
+    if !has_signature(data, index, &LOCAL_FILE_HEADER) || index + LOCAL_FILE_HEADER_LENGTH > data.len() {
+        return None;
+    }
+
+    let version_needed: u16 = read_u16(data, index + 4);
+    let method: u16 = read_u16(data, index + 8);
+    let name_length: usize = read_u16(data, index + 26) as usize;
+    let extra_length: usize = read_u16(data, index + 28) as usize;
+    if version_needed & 0xFF > 100 || !(KNOWN_METHODS.contains(&method) || method == AES_ENCRYPTED) {
+        return None;
+    }
+    if name_length == 0 || name_length > MAX_NAME_LENGTH {
+        return None;
+    }
+
+    let name_start: usize = index + LOCAL_FILE_HEADER_LENGTH;
+    let name_end: usize = (name_start + name_length).min(data.len());
+    if data[name_start..name_end].iter().any(|byte| *byte < 0x20) {
+        return None;
+    }
+
+    return Some(name_start + name_length + extra_length);
+}
+
+// Follows local file headers from start_index as long as their sizes are known,
+// returns an index where the entries break off into something else and how many entries were
+// walked. None means that entries continue past the end of data or can't be followed
+fn walk_local_entries(data: &[u8], start_index: usize) -> Option<(usize, usize)> {
+    let mut entry_count: usize = 0;
+    let mut i: usize = start_index;
+    loop {
+        if i + 4 > data.len() {
+            return None;
+        }
+        if has_signature(data, i, &CENTRAL_DIRECTORY_HEADER) {
+            // the rest of the archive should have been found by its end record
+            return None;
+        }
+
+        let data_start: usize;
+        match check_local_header(data, i) {
+            Some(index) => {
+                data_start = index;
+            }
+            None => {
+                if i + LOCAL_FILE_HEADER_LENGTH > data.len() && has_signature(data, i, &LOCAL_FILE_HEADER) {
+                    return None;
+                }
+                return Some((i, entry_count));
+            }
+        }
+
+        let flags: u16 = read_u16(data, i + 6);
+        let compressed_size: usize = read_u32(data, i + 18) as usize;
+        if flags & HAS_DATA_DESCRIPTOR != 0 && compressed_size == 0 {
+            // the size is only known after the data
+            return None;
+        }
+        entry_count += 1;
+
+        i = data_start + compressed_size;
+        if flags & HAS_DATA_DESCRIPTOR != 0 {
+            // optional signature, CRC-32 and sizes of 4 or 8 bytes each
+            if has_signature(data, i, &DATA_DESCRIPTOR) {
+                i += 4;
+            }
+            if i + 12 + 4 <= data.len() && data[i + 12..i + 14] == *b"PK" {
+                i += 12;
+            } else {
+                i += 20;
+            }
+        }
+    }
+}
+
+// Tries to read a ZIP archive that starts exactly at start_index, if a valid archive
+// was found - returns positions of it from the first local header to the end of the archive comment.
+// The archive is found by the end of central directory record whose central directory
+// checks out and points back at start_index
+pub fn rip_zip(data: &[u8], start_index: usize) -> Option<Position> {
+    let entries_start: usize = check_local_header(data, start_index)?;
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: ContentType::ZIP,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    // archives stored inside of this one end earlier, so look for the first record that fits
+    let mut search_index: usize = entries_start;
+    while let Some(eocd_index) = find_signature(data, search_index, &END_OF_CENTRAL_DIRECTORY) {
+        search_index = eocd_index + 1;
+
+        let directory: CentralDirectory;
+        match read_central_directory(data, eocd_index) {
+            Some(found_directory) => {
+                directory = found_directory;
+            }
+            None => {
+                continue;
+            }
+        }
+
+        match directory.members.iter().map(|member| member.local_header_index).min() {
+            Some(first_index) if first_index == start_index => {}
+            Some(first_index) if first_index < start_index && directory.members.iter().any(|member| member.local_header_index == start_index) => {
+                // a member of an archive whose beginning could not be read, take the rest of it
+                // so that its other members are not looked at one by one
+                position.validity = Validity::CORRUPT;
+            }
+            _ => {
+                continue;
+            }
+        }
+
+        for member in directory.members.iter() {
+            if member.local_header_index + 4 <= data.len() && !has_signature(data, member.local_header_index, &LOCAL_FILE_HEADER) {
+                // the directory points at something else
+                position.validity = Validity::CORRUPT;
+            }
+        }
+
+        position.end = directory.end;
+        if position.end > data.len() {
+            // the comment goes further than the data
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+        }
+
+        position.metadata.push(("entries", directory.members.len().to_string()));
+        if directory.is_zip64 {
+            position.metadata.push(("zip64", String::from("true")));
+        }
+        return Some(position);
+    }
+
+    // no central directory, see how far the entries go
+    match walk_local_entries(data, start_index) {
+        Some((_, 0)) => {
+            // not even a single entry, that was a coincidence
+            return None;
+        }
+        Some((break_index, _)) => {
+            position.end = break_index;
+        }
+        None => {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+        }
+    }
+    position.validity = Validity::TRUNCATED;
+
+    return Some(position);
+}
+
+// Returns members of a ZIP archive that takes up the whole archive slice
+pub fn zip_members(archive: &[u8]) -> Option<Vec<ZipMember>> {
+    if archive.len() < END_OF_CENTRAL_DIRECTORY_LENGTH {
+        return None;
+    }
+
+    // the record is followed by a comment of unknown length, go backwards
+    let last_index: usize = archive.len() - END_OF_CENTRAL_DIRECTORY_LENGTH;
+    let first_index: usize = last_index.saturating_sub(MAX_COMMENT_LENGTH);
+    for eocd_index in (first_index..=last_index).rev() {
+        if !has_signature(archive, eocd_index, &END_OF_CENTRAL_DIRECTORY) {
+            continue;
+        }
+
+        if let Some(directory) = read_central_directory(archive, eocd_index) {
+            if directory.end == archive.len() {
+                return Some(directory.members);
+            }
+        }
+    }
+
+    return None;
+}
+
+// Returns decompressed contents of a stored or deflated member of the archive,
+// refusing to produce more than max_length bytes
+pub fn extract_zip_member(archive: &[u8], member: &ZipMember, max_length: usize) -> Result<Vec<u8>, MemberError> {
+    if member.is_encrypted() || (member.method != STORED && member.method != DEFLATED) {
+        return Err(MemberError::UNSUPPORTED);
+    }
+    if member.uncompressed_size > max_length as u64 {
+        return Err(MemberError::OVERSIZED);
+    }
+
+    // sizes in the local header may be missing, the central directory knows better
+    let index: usize = member.local_header_index;
+    if !has_signature(archive, index, &LOCAL_FILE_HEADER) || index + LOCAL_FILE_HEADER_LENGTH > archive.len() {
+        return Err(MemberError::MALFORMED);
+    }
+    let data_start: usize = index + LOCAL_FILE_HEADER_LENGTH + read_u16(archive, index + 26) as usize + read_u16(archive, index + 28) as usize;
+    if member.compressed_size > (archive.len() as u64).saturating_sub(data_start as u64) {
+        return Err(MemberError::MALFORMED);
+    }
+    let compressed: &[u8] = &archive[data_start..data_start + member.compressed_size as usize];
+
+    let contents: Vec<u8>;
+    if member.method == STORED {
+        contents = compressed.to_vec();
+    } else {
+        match inflate(compressed, max_length) {
+            Ok((decompressed, _)) => {
+                contents = decompressed;
+            }
+            Err(InflateError::OVERSIZED) => {
+                return Err(MemberError::OVERSIZED);
+            }
+            Err(_) => {
+                return Err(MemberError::MALFORMED);
+            }
+        }
+    }
+
+    if contents.len() as u64 != member.uncompressed_size || crc32(&contents) != member.crc32 {
+        return Err(MemberError::CORRUPT);
+    }
+
+    return Ok(contents);
+}
+
+pub struct ZipRipper;
+
+impl Ripper for ZipRipper {
+    fn name(&self) -> &'static str {
+        return "ZIP";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::ZIP;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::ARCHIVE;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&LOCAL_FILE_HEADER];
+    }
+
+    // every member starts with a local header, each of them would be
+    // looked for the end of an archive otherwise
+    fn can_nest(&self) -> bool {
+        return false;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_zip(data, start_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestMember<'a> {
+        name: &'a str,
+        contents: &'a [u8],
+        flags: u16,
+        // local header offset as the central directory tells it, with the ZIP64 extra field holding it
+        zip64_offset: Option<u64>,
+    }
+
+    fn member<'a>(name: &'a str, contents: &'a [u8]) -> TestMember<'a> {
+        return TestMember{
+            name: name,
+            contents: contents,
+            flags: 0,
+            zip64_offset: None,
+        };
+    }
+
+    // Builds a stored archive, ZIP64 records are added when any member asks for them
+    fn build_archive(members: &[TestMember]) -> Vec<u8> {
+        let mut archive: Vec<u8> = Vec::new();
+        let mut directory: Vec<u8> = Vec::new();
+        let mut is_zip64: bool = false;
+
+        for member in members.iter() {
+            let local_header_offset: u32 = archive.len() as u32;
+            let crc: u32 = crc32(member.contents);
+            let size: u32 = member.contents.len() as u32;
+            let has_descriptor: bool = member.flags & HAS_DATA_DESCRIPTOR != 0;
+
+            archive.extend_from_slice(&LOCAL_FILE_HEADER);
+            archive.extend_from_slice(&20u16.to_le_bytes());
+            archive.extend_from_slice(&member.flags.to_le_bytes());
+            archive.extend_from_slice(&STORED.to_le_bytes());
+            archive.extend_from_slice(&[0; 4]);
+            for value in [crc, size, size] {
+                archive.extend_from_slice(&(if has_descriptor {0} else {value}).to_le_bytes());
+            }
+            archive.extend_from_slice(&(member.name.len() as u16).to_le_bytes());
+            archive.extend_from_slice(&0u16.to_le_bytes());
+            archive.extend_from_slice(member.name.as_bytes());
+            archive.extend_from_slice(member.contents);
+            if has_descriptor {
+                archive.extend_from_slice(&DATA_DESCRIPTOR);
+                for value in [crc, size, size] {
+                    archive.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+
+            let mut extra: Vec<u8> = Vec::new();
+            let mut directory_offset: u32 = local_header_offset;
+            if let Some(offset) = member.zip64_offset {
+                is_zip64 = true;
+                directory_offset = 0xFFFFFFFF;
+                extra.extend_from_slice(&ZIP64_EXTRA_FIELD.to_le_bytes());
+                extra.extend_from_slice(&8u16.to_le_bytes());
+                extra.extend_from_slice(&offset.to_le_bytes());
+            }
+
+            directory.extend_from_slice(&CENTRAL_DIRECTORY_HEADER);
+            directory.extend_from_slice(&[20, 0, 20, 0]);
+            directory.extend_from_slice(&member.flags.to_le_bytes());
+            directory.extend_from_slice(&STORED.to_le_bytes());
+            directory.extend_from_slice(&[0; 4]);
+            for value in [crc, size, size] {
+                directory.extend_from_slice(&value.to_le_bytes());
+            }
+            directory.extend_from_slice(&(member.name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&(extra.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&[0; 10]);
+            directory.extend_from_slice(&directory_offset.to_le_bytes());
+            directory.extend_from_slice(member.name.as_bytes());
+            directory.extend_from_slice(&extra);
+        }
+
+        let directory_offset: u64 = archive.len() as u64;
+        let directory_size: u64 = directory.len() as u64;
+        archive.extend_from_slice(&directory);
+
+        let entry_count: u16 = members.len() as u16;
+        if is_zip64 {
+            let record_offset: u64 = archive.len() as u64;
+            archive.extend_from_slice(&ZIP64_END_OF_CENTRAL_DIRECTORY);
+            archive.extend_from_slice(&44u64.to_le_bytes());
+            archive.extend_from_slice(&[45, 0, 45, 0]);
+            archive.extend_from_slice(&[0; 8]);
+            for value in [members.len() as u64, members.len() as u64, directory_size, directory_offset] {
+                archive.extend_from_slice(&value.to_le_bytes());
+            }
+
+            archive.extend_from_slice(&ZIP64_LOCATOR);
+            archive.extend_from_slice(&0u32.to_le_bytes());
+            archive.extend_from_slice(&record_offset.to_le_bytes());
+            archive.extend_from_slice(&1u32.to_le_bytes());
+        }
+
+        archive.extend_from_slice(&END_OF_CENTRAL_DIRECTORY);
+        archive.extend_from_slice(&[0; 4]);
+        archive.extend_from_slice(&entry_count.to_le_bytes());
+        archive.extend_from_slice(&entry_count.to_le_bytes());
+        archive.extend_from_slice(&(directory_size as u32).to_le_bytes());
+        archive.extend_from_slice(&(directory_offset as u32).to_le_bytes());
+        archive.extend_from_slice(&0u16.to_le_bytes());
+
+        return archive;
+    }
+
+    #[test]
+    fn archive_is_found_after_leading_bytes() {
+        let mut data: Vec<u8> = vec![0xAA; 7];
+        data.extend_from_slice(&build_archive(&[member("a.txt", b"hello"), member("dir/b.txt", b"world")]));
+
+        let position: Position = rip_zip(&data, 7).expect("archive was not found");
+        assert_eq!(position.end, data.len());
+        assert_eq!(position.validity, Validity::VALID);
+
+        let members: Vec<ZipMember> = zip_members(&data[7..]).expect("members were not read");
+        assert_eq!(members.len(), 2);
+        assert_eq!(extract_zip_member(&data[7..], &members[1], 100), Ok(b"world".to_vec()));
+        assert_eq!(extract_zip_member(&data[7..], &members[1], 4), Err(MemberError::OVERSIZED));
+    }
+
+    #[test]
+    fn zip64_records_are_read() {
+        let archive: Vec<u8> = build_archive(&[TestMember{
+            zip64_offset: Some(0),
+            ..member("a.txt", b"hello")
+        }]);
+
+        let position: Position = rip_zip(&archive, 0).expect("archive was not found");
+        assert_eq!(position.end, archive.len());
+        assert_eq!(position.validity, Validity::VALID);
+        assert!(position.metadata.contains(&("zip64", String::from("true"))));
+
+        let members: Vec<ZipMember> = zip_members(&archive).expect("members were not read");
+        assert_eq!(extract_zip_member(&archive, &members[0], 100), Ok(b"hello".to_vec()));
+    }
+
+    #[test]
+    fn overflowing_zip64_offset_is_left_out() {
+        let mut data: Vec<u8> = vec![0xAA; 16];
+        data.extend_from_slice(&build_archive(&[TestMember{
+            zip64_offset: Some(i64::MAX as u64 - 1),
+            ..member("a.txt", b"hello")
+        }]));
+
+        let eocd_index: usize = data.len() - END_OF_CENTRAL_DIRECTORY_LENGTH;
+        let directory: CentralDirectory = read_central_directory(&data, eocd_index).expect("directory was not read");
+        assert_eq!(directory.members.len(), 0);
+    }
+
+    #[test]
+    fn data_descriptors_are_followed() {
+        let members: [TestMember; 2] = [
+            TestMember{
+                flags: HAS_DATA_DESCRIPTOR,
+                ..member("a.txt", b"hello")
+            },
+            member("b.txt", b"world"),
+        ];
+        let archive: Vec<u8> = build_archive(&members);
+
+        let position: Position = rip_zip(&archive, 0).expect("archive was not found");
+        assert_eq!(position.end, archive.len());
+        assert_eq!(position.validity, Validity::VALID);
+        let found_members: Vec<ZipMember> = zip_members(&archive).expect("members were not read");
+        assert_eq!(extract_zip_member(&archive, &found_members[0], 100), Ok(b"hello".to_vec()));
+
+        // without the central directory the size is only known after the data
+        let directory_start: usize = found_members[1].local_header_index + LOCAL_FILE_HEADER_LENGTH + 5 + 5;
+        let position: Position = rip_zip(&archive[..directory_start], 0).expect("entries were not found");
+        assert_eq!(position.end, directory_start);
+        assert_eq!(position.validity, Validity::TRUNCATED);
+    }
+
+    #[test]
+    fn names_leaving_the_directory_are_refused() {
+        let mut zip_member: ZipMember = ZipMember{
+            name: String::from("../evil.txt"),
+            flags: 0,
+            method: STORED,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            local_header_index: 0,
+        };
+        assert_eq!(zip_member.relative_path(), None);
+
+        zip_member.name = String::from("dir/../../evil.txt");
+        assert_eq!(zip_member.relative_path(), None);
+
+        zip_member.name = String::from("C:evil.txt");
+        assert_eq!(zip_member.relative_path(), None);
+
+        zip_member.name = String::from("./dir//a.txt");
+        assert_eq!(zip_member.relative_path(), Some(PathBuf::from("dir").join("a.txt")));
+    }
+}
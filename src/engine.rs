@@ -0,0 +1,114 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::scanner::Scanner;
+use crate::util::ripper::Ripper;
+use crate::registry::rippers_for;
+use crate::ScanOptions;
+
+// Content found at a signature and the ripper that found it
+pub(crate) struct Candidate {
+    pub ripper_index: usize,
+    pub position: Position,
+}
+
+// Selected rippers with a scanner that looks for all of their signatures at once
+pub(crate) struct Engine {
+    pub rippers: Vec<Box<dyn Ripper>>,
+    // which ripper each signature belongs to
+    signature_owners: Vec<usize>,
+    // how far from the start of the content each signature is
+    signature_offsets: Vec<usize>,
+    scanner: Scanner,
+}
+
+impl Engine {
+    pub fn new(options: &ScanOptions) -> Engine {
+        let rippers: Vec<Box<dyn Ripper>> = rippers_for(options);
+
+        let mut signatures: Vec<&[u8]> = Vec::new();
+        let mut signature_owners: Vec<usize> = Vec::new();
+        let mut signature_offsets: Vec<usize> = Vec::new();
+        for (ripper_index, ripper) in rippers.iter().enumerate() {
+            for signature in ripper.magic() {
+                signatures.push(signature);
+                signature_owners.push(ripper_index);
+                signature_offsets.push(ripper.magic_offset());
+            }
+        }
+        let scanner: Scanner = Scanner::new(&signatures);
+
+        return Engine{
+            rippers: rippers,
+            signature_owners: signature_owners,
+            signature_offsets: signature_offsets,
+            scanner: scanner,
+        };
+    }
+
+    // Returns how far past the start of content its signature may end
+    pub fn max_signature_length(&self) -> usize {
+        let max_offset: usize = self.signature_offsets.iter().copied().max().unwrap_or(0);
+        return self.scanner.max_signature_length() + max_offset;
+    }
+
+    // Returns limits for rip to start with
+    pub fn new_skip_limits(&self) -> Vec<usize> {
+        return vec![0; self.rippers.len()];
+    }
+
+    // Finds every signature of content that starts before scan_until in one pass and lets
+    // the corresponding parsers decide whether there's actually something.
+    // Rippers that can't nest skip signatures before skip_until[ripper_index],
+    // which gets moved past the content they rip
+    pub fn rip(&self, data: &[u8], scan_until: usize, skip_until: &mut [usize]) -> Vec<Candidate> {
+        let scan_end: usize = std::cmp::min(data.len(), scan_until + self.max_signature_length());
+
+        let mut candidates: Vec<Candidate> = Vec::new();
+        for signature_match in self.scanner.scan(&data[..scan_end]) {
+            let signature_offset: usize = self.signature_offsets[signature_match.signature];
+            if signature_match.start < signature_offset {
+                // content would have started before data does
+                continue;
+            }
+
+            let content_start: usize = signature_match.start - signature_offset;
+            if content_start >= scan_until {
+                continue;
+            }
+
+            let ripper_index: usize = self.signature_owners[signature_match.signature];
+            if content_start < skip_until[ripper_index] {
+                continue;
+            }
+
+            if let Some(pos) = self.rippers[ripper_index].rip(data, content_start) {
+                if !self.rippers[ripper_index].can_nest() {
+                    skip_until[ripper_index] = pos.end;
+                }
+                candidates.push(Candidate{
+                    ripper_index: ripper_index,
+                    position: pos,
+                });
+            }
+        }
+
+        return candidates;
+    }
+}
@@ -0,0 +1,384 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+
+const BMP_IDENTIFIER: [u8; 2] = *b"BM";
+const FILE_HEADER_LENGTH: usize = 14;
+
+// DIB header sizes, the only thing a headerless DIB can be recognised by
+const CORE_HEADER_LENGTH: usize = 12;
+const INFO_HEADER_LENGTH: usize = 40;
+const V4_HEADER_LENGTH: usize = 108;
+const V5_HEADER_LENGTH: usize = 124;
+const INFO_HEADER_IDENTIFIER: [u8; 4] = [INFO_HEADER_LENGTH as u8, 0, 0, 0];
+const V4_HEADER_IDENTIFIER: [u8; 4] = [V4_HEADER_LENGTH as u8, 0, 0, 0];
+const V5_HEADER_IDENTIFIER: [u8; 4] = [V5_HEADER_LENGTH as u8, 0, 0, 0];
+
+// compression methods
+const BI_RGB: u32 = 0;
+const BI_RLE8: u32 = 1;
+const BI_RLE4: u32 = 2;
+const BI_BITFIELDS: u32 = 3;
+const BI_JPEG: u32 = 4;
+const BI_PNG: u32 = 5;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+// "MBED" colour space of a V5 header that has an ICC profile embedded after the pixels
+const PROFILE_EMBEDDED: u32 = 0x4D424544;
+const MAX_DIMENSION: u32 = 0xFFFF;
+// some writers pad the declared file size to a multiple of 4
+const MAX_FILE_SIZE_SLACK: usize = 4;
+
+// Everything a DIB header says about the layout of the bitmap
+struct DibHeader {
+    header_length: usize,
+    width: u32,
+    height: u32,
+    bit_count: u16,
+    // length of bit masks and colour table that follow the header
+    tables_length: usize,
+    // length of pixel data
+    image_length: usize,
+    // end of an embedded colour profile relative to the header start, 0 if there's none
+    profile_end: usize,
+}
+
+fn read_u16(data: &[u8], index: usize) -> u16 {
+    return u16::from_le_bytes([data[index], data[index + 1]]);
+}
+
+fn read_u32(data: &[u8], index: usize) -> u32 {
+    return u32::from_le_bytes([data[index], data[index + 1], data[index + 2], data[index + 3]]);
+}
+
+// Parses and sanity-checks a DIB header at header_start.
+// Headers with fields that contradict each other are rejected
+fn read_dib_header(data: &[u8], header_start: usize) -> Option<DibHeader> {
+    if header_start + 4 > data.len() {
+        return None;
+    }
+
+    let header_length: usize = read_u32(data, header_start) as usize;
+    if header_start + header_length > data.len() {
+        return None;
+    }
+
+    if header_length == CORE_HEADER_LENGTH {
+        // OS/2 BITMAPCOREHEADER
+        let width: u32 = read_u16(data, header_start + 4) as u32;
+        let height: u32 = read_u16(data, header_start + 6) as u32;
+        let planes: u16 = read_u16(data, header_start + 8);
+        let bit_count: u16 = read_u16(data, header_start + 10);
+        if width == 0 || height == 0 || planes != 1 || ![1, 4, 8, 24].contains(&bit_count) {
+            return None;
+        }
+
+        let row_length: usize = (width as usize * bit_count as usize).div_ceil(32) * 4;
+        return Some(DibHeader{
+            header_length: header_length,
+            width: width,
+            height: height,
+            bit_count: bit_count,
+            // 3-byte palette entries
+            tables_length: if bit_count <= 8 {3 << bit_count} else {0},
+            image_length: row_length * height as usize,
+            profile_end: 0,
+        });
+    }
+
+    if ![INFO_HEADER_LENGTH, 52, 56, 64, V4_HEADER_LENGTH, V5_HEADER_LENGTH].contains(&header_length) {
+        return None;
+    }
+
+    let width: i32 = read_u32(data, header_start + 4) as i32;
+    let height: i32 = read_u32(data, header_start + 8) as i32;
+    let planes: u16 = read_u16(data, header_start + 12);
+    let bit_count: u16 = read_u16(data, header_start + 14);
+    let compression: u32 = read_u32(data, header_start + 16);
+    let declared_image_length: usize = read_u32(data, header_start + 20) as usize;
+    let colors_used: usize = read_u32(data, header_start + 32) as usize;
+
+    if width <= 0 || width as u32 > MAX_DIMENSION || height == 0 || height.unsigned_abs() > MAX_DIMENSION || planes != 1 {
+        return None;
+    }
+
+    let is_valid_combination: bool = match compression {
+        BI_RGB => [1, 2, 4, 8, 16, 24, 32].contains(&bit_count),
+        BI_RLE8 => bit_count == 8 && height > 0,
+        BI_RLE4 => bit_count == 4 && height > 0,
+        BI_BITFIELDS | BI_ALPHABITFIELDS => bit_count == 16 || bit_count == 32,
+        BI_JPEG | BI_PNG => bit_count == 0 && height > 0,
+        _ => false,
+    };
+    if !is_valid_combination {
+        return None;
+    }
+
+    // there can't be more colours than there are pixel values
+    if bit_count <= 8 && bit_count != 0 && colors_used > (1 << bit_count) {
+        return None;
+    }
+    if colors_used > 1 << 16 {
+        return None;
+    }
+
+    let mut tables_length: usize = 0;
+    if header_length == INFO_HEADER_LENGTH {
+        // bit masks follow BITMAPINFOHEADER, bigger headers include them
+        if compression == BI_BITFIELDS {
+            tables_length += 12;
+        }
+        else if compression == BI_ALPHABITFIELDS {
+            tables_length += 16;
+        }
+    }
+    if colors_used != 0 {
+        tables_length += colors_used * 4;
+    }
+    else if bit_count != 0 && bit_count <= 8 {
+        tables_length += 4 << bit_count;
+    }
+
+    let image_length: usize;
+    if compression == BI_RGB || compression == BI_BITFIELDS || compression == BI_ALPHABITFIELDS {
+        let row_length: usize = (width as usize * bit_count as usize).div_ceil(32) * 4;
+        image_length = row_length * height.unsigned_abs() as usize;
+        if declared_image_length != 0 && declared_image_length != image_length {
+            return None;
+        }
+    }
+    else {
+        // compressed data can only be measured by the declared length
+        if declared_image_length == 0 {
+            return None;
+        }
+        image_length = declared_image_length;
+    }
+
+    let mut profile_end: usize = 0;
+    if header_length == V5_HEADER_LENGTH && read_u32(data, header_start + 56) == PROFILE_EMBEDDED {
+        let profile_offset: usize = read_u32(data, header_start + 112) as usize;
+        let profile_length: usize = read_u32(data, header_start + 116) as usize;
+        if profile_offset < header_length {
+            return None;
+        }
+        profile_end = profile_offset + profile_length;
+    }
+
+    return Some(DibHeader{
+        header_length: header_length,
+        width: width as u32,
+        height: height.unsigned_abs(),
+        bit_count: bit_count,
+        tables_length: tables_length,
+        image_length: image_length,
+        profile_end: profile_end,
+    });
+}
+
+// Tries to read a bmp file that starts exactly at start_index.
+// The declared file size is trusted only if it agrees with the DIB header,
+// otherwise the bitmap is carved by the header and marked as corrupt
+fn rip_bmp_file(data: &[u8], start_index: usize) -> Option<Position> {
+    if start_index + FILE_HEADER_LENGTH > data.len() {
+        return None;
+    }
+
+    let declared_file_size: usize = read_u32(data, start_index + 2) as usize;
+    let reserved: u32 = read_u32(data, start_index + 6);
+    let pixel_data_offset: usize = read_u32(data, start_index + 10) as usize;
+    if reserved != 0 {
+        return None;
+    }
+
+    let dib_header: DibHeader = read_dib_header(data, start_index + FILE_HEADER_LENGTH)?;
+
+    // pixels can't start inside of the headers
+    if pixel_data_offset < FILE_HEADER_LENGTH + dib_header.header_length + dib_header.tables_length {
+        return None;
+    }
+
+    let mut expected_file_size: usize = pixel_data_offset + dib_header.image_length;
+    if dib_header.profile_end != 0 {
+        expected_file_size = std::cmp::max(expected_file_size, FILE_HEADER_LENGTH + dib_header.profile_end);
+    }
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: start_index + expected_file_size,
+        content_type: ContentType::BMP,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    if declared_file_size != 0 {
+        if declared_file_size < expected_file_size {
+            // the header promises less than the bitmap needs
+            return None;
+        }
+        if declared_file_size - expected_file_size <= MAX_FILE_SIZE_SLACK {
+            position.end = start_index + declared_file_size;
+        }
+        else {
+            position.validity = Validity::CORRUPT;
+        }
+    }
+
+    push_metadata(&mut position, &dib_header);
+
+    return Some(position);
+}
+
+// Tries to read a bitmap without a file header, as stored in resources or clipboard data
+fn rip_dib(data: &[u8], start_index: usize) -> Option<Position> {
+    let dib_header: DibHeader = read_dib_header(data, start_index)?;
+
+    let mut length: usize = dib_header.header_length + dib_header.tables_length + dib_header.image_length;
+    length = std::cmp::max(length, dib_header.profile_end);
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: start_index + length,
+        content_type: ContentType::DIB,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    push_metadata(&mut position, &dib_header);
+
+    return Some(position);
+}
+
+fn push_metadata(position: &mut Position, dib_header: &DibHeader) {
+    position.metadata.push(("width", dib_header.width.to_string()));
+    position.metadata.push(("height", dib_header.height.to_string()));
+    position.metadata.push(("bit_depth", dib_header.bit_count.to_string()));
+}
+
+// Tries to read a bmp file or a headerless DIB that starts exactly at start_index,
+// if valid bitmap bytes were found - returns exact positions of a bitmap
+pub fn rip_bmp(data: &[u8], start_index: usize) -> Option<Position> {
+    let mut position: Position;
+    if data[start_index..].starts_with(&BMP_IDENTIFIER) {
+        position = rip_bmp_file(data, start_index)?;
+    }
+    else {
+        position = rip_dib(data, start_index)?;
+        if position.end > data.len() {
+            // four bytes of a header size are all there is to go by, a header that
+            // claims more than there is must have been made up by chance
+            return None;
+        }
+    }
+
+    if position.end > data.len() {
+        // no end in sight, it might be further than the data goes
+        position.end = data.len();
+        position.validity = Validity::TRUNCATED;
+    }
+
+    return Some(position);
+}
+
+pub struct BmpRipper;
+
+impl Ripper for BmpRipper {
+    fn name(&self) -> &'static str {
+        return "BMP";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::BMP;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::IMG;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&BMP_IDENTIFIER, &INFO_HEADER_IDENTIFIER, &V4_HEADER_IDENTIFIER, &V5_HEADER_IDENTIFIER];
+    }
+
+    // a bmp file's own DIB header must not be ripped again as a headerless DIB
+    fn can_nest(&self) -> bool {
+        return false;
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_bmp(data, start_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // BITMAPINFOHEADER of an uncompressed 24-bit bitmap
+    fn info_header(width: u32, height: u32) -> Vec<u8> {
+        let mut header: Vec<u8> = Vec::new();
+        header.extend_from_slice(&(INFO_HEADER_LENGTH as u32).to_le_bytes());
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&24u16.to_le_bytes());
+        header.extend_from_slice(&[0; 24]);
+        return header;
+    }
+
+    #[test]
+    fn headerless_dib_is_carved_by_its_header() {
+        let mut data: Vec<u8> = info_header(2, 2);
+        // two rows of 6 bytes padded to 8
+        data.extend_from_slice(&[0x11; 16]);
+        data.extend_from_slice(&[0xEE; 10]);
+
+        let position: Position = rip_bmp(&data, 0).expect("bitmap was not found");
+        assert_eq!(position.end, INFO_HEADER_LENGTH + 16);
+        assert_eq!(position.validity, Validity::VALID);
+    }
+
+    #[test]
+    fn headerless_dib_longer_than_data_is_rejected() {
+        let mut data: Vec<u8> = info_header(MAX_DIMENSION, MAX_DIMENSION);
+        data.extend_from_slice(&[0x11; 1000]);
+
+        assert!(rip_bmp(&data, 0).is_none());
+    }
+
+    #[test]
+    fn cut_off_bmp_file_is_truncated() {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend_from_slice(&BMP_IDENTIFIER);
+        data.extend_from_slice(&((FILE_HEADER_LENGTH + INFO_HEADER_LENGTH + 16) as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&((FILE_HEADER_LENGTH + INFO_HEADER_LENGTH) as u32).to_le_bytes());
+        data.extend_from_slice(&info_header(2, 2));
+        data.extend_from_slice(&[0x11; 10]);
+
+        let position: Position = rip_bmp(&data, 0).expect("bitmap was not found");
+        assert_eq!(position.end, data.len());
+        assert_eq!(position.validity, Validity::TRUNCATED);
+    }
+}
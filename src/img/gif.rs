@@ -0,0 +1,202 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+
+const GIF87A_IDENTIFIER: [u8; 6] = *b"GIF87a";
+const GIF89A_IDENTIFIER: [u8; 6] = *b"GIF89a";
+// signature + logical screen descriptor
+const HEADER_LENGTH: usize = 13;
+// separator + image position, dimensions and flags
+const IMAGE_DESCRIPTOR_LENGTH: usize = 10;
+
+// blocks
+const EXTENSION_INTRODUCER: u8 = 0x21;
+const IMAGE_SEPARATOR: u8 = 0x2C;
+const TRAILER: u8 = 0x3B;
+
+// Returns the size of a colour table described by packed fields if there is one
+fn color_table_length(packed_fields: u8) -> usize {
+    if packed_fields & 0x80 == 0 {
+        return 0;
+    }
+    return 3 * (1 << ((packed_fields & 0x07) + 1));
+}
+
+// Goes over a sequence of data sub-blocks that begins at start_index,
+// returns the index right after the block terminator or None if data ends before it
+fn skip_sub_blocks(data: &[u8], start_index: usize) -> Option<usize> {
+    let mut i: usize = start_index;
+    while i < data.len() {
+        let block_size: usize = data[i] as usize;
+        i += 1 + block_size;
+        if block_size == 0 {
+            return Some(i);
+        }
+    }
+
+    return None;
+}
+
+// Tries to read a gif that starts exactly at start_index by walking its blocks,
+// if valid gif bytes were found - returns exact positions of an image including the trailer.
+// Images that break off after at least one frame are cut there and marked as truncated
+pub fn rip_gif(data: &[u8], start_index: usize) -> Option<Position> {
+    if start_index + GIF89A_IDENTIFIER.len() > data.len() {
+        return None;
+    }
+
+    let signature: &[u8] = &data[start_index..start_index + GIF89A_IDENTIFIER.len()];
+    if signature != GIF87A_IDENTIFIER && signature != GIF89A_IDENTIFIER {
+        return None;
+    }
+
+    let mut position: Position = Position{
+        start: start_index,
+        end: usize::MAX,
+        content_type: ContentType::GIF,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
+    };
+
+    if start_index + HEADER_LENGTH > data.len() {
+        position.end = data.len();
+        position.validity = Validity::TRUNCATED;
+        return Some(position);
+    }
+
+    // logical screen descriptor
+    let screen_width: u16 = u16::from_le_bytes([data[start_index + 6], data[start_index + 7]]);
+    let screen_height: u16 = u16::from_le_bytes([data[start_index + 8], data[start_index + 9]]);
+    let screen_packed_fields: u8 = data[start_index + 10];
+
+    let mut frame_count: usize = 0;
+    let mut i: usize = start_index + HEADER_LENGTH + color_table_length(screen_packed_fields);
+    while position.end == usize::MAX {
+        if i >= data.len() {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        match data[i] {
+            TRAILER => {
+                if frame_count == 0 {
+                    // there was no actual image
+                    return None;
+                }
+                position.end = i + 1;
+            }
+
+            EXTENSION_INTRODUCER => {
+                // introducer, label, then sub-blocks
+                match skip_sub_blocks(data, i + 2) {
+                    Some(block_end) if block_end <= data.len() => i = block_end,
+                    _ => {
+                        position.end = data.len();
+                        position.validity = Validity::TRUNCATED;
+                    }
+                }
+            }
+
+            IMAGE_SEPARATOR => {
+                if i + IMAGE_DESCRIPTOR_LENGTH + 1 > data.len() {
+                    position.end = data.len();
+                    position.validity = Validity::TRUNCATED;
+                    break;
+                }
+
+                let image_packed_fields: u8 = data[i + 9];
+                let image_data_start: usize = i + IMAGE_DESCRIPTOR_LENGTH + color_table_length(image_packed_fields);
+                if image_data_start >= data.len() {
+                    position.end = data.len();
+                    position.validity = Validity::TRUNCATED;
+                    break;
+                }
+
+                // LZW minimum code size is followed by the compressed image sub-blocks
+                let lzw_code_size: u8 = data[image_data_start];
+                if !(2..=8).contains(&lzw_code_size) {
+                    if frame_count == 0 {
+                        return None;
+                    }
+                    position.end = i;
+                    position.validity = Validity::TRUNCATED;
+                    break;
+                }
+
+                match skip_sub_blocks(data, image_data_start + 1) {
+                    Some(block_end) if block_end <= data.len() => {
+                        frame_count += 1;
+                        i = block_end;
+                    }
+                    _ => {
+                        position.end = data.len();
+                        position.validity = Validity::TRUNCATED;
+                    }
+                }
+            }
+
+            _ => {
+                if frame_count == 0 {
+                    // garbage where a block should be
+                    return None;
+                }
+                // the image ends prematurely and something else follows
+                position.end = i;
+                position.validity = Validity::TRUNCATED;
+            }
+        }
+    }
+
+    position.metadata.push(("width", screen_width.to_string()));
+    position.metadata.push(("height", screen_height.to_string()));
+    position.metadata.push(("frames", frame_count.to_string()));
+
+    return Some(position);
+}
+
+pub struct GifRipper;
+
+impl Ripper for GifRipper {
+    fn name(&self) -> &'static str {
+        return "GIF";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::GIF;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::IMG;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&GIF87A_IDENTIFIER, &GIF89A_IDENTIFIER];
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_gif(data, start_index);
+    }
+}
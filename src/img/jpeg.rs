@@ -18,47 +18,195 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::util::position::Position;
 use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
 
 const JPEG_IDENTIFIER: [u8; 3] = [0xFF, 0xD8, 0xFF];
-const JPEG_END_IDENTIFIER: [u8; 2] = [0xFF, 0xD9];
 
-// Reads data from specified start_index position,
-// if valid png bytes were found - returns exact positions of an image
+// markers
+const SOI: u8 = 0xD8;
+const EOI: u8 = 0xD9;
+const SOS: u8 = 0xDA;
+const DQT: u8 = 0xDB;
+const DHT: u8 = 0xC4;
+const DRI: u8 = 0xDD;
+const COM: u8 = 0xFE;
+const TEM: u8 = 0x01;
+
+fn is_rst(marker: u8) -> bool {
+    return (0xD0..=0xD7).contains(&marker);
+}
+
+fn is_app(marker: u8) -> bool {
+    return (0xE0..=0xEF).contains(&marker);
+}
+
+// SOF0..SOF15 except for DHT, JPG and DAC that share the range
+fn is_sof(marker: u8) -> bool {
+    return (0xC0..=0xCF).contains(&marker) && marker != DHT && marker != 0xC8 && marker != 0xCC;
+}
+
+// Returns the index right after the entropy-coded data that begins at start_index,
+// that is an index of the next non-RST marker or data.len() if there's none
+fn skip_entropy_coded_data(data: &[u8], start_index: usize) -> usize {
+    let mut i: usize = start_index;
+    while i + 1 < data.len() {
+        if data[i] != 0xFF {
+            i += 1;
+            continue;
+        }
+
+        match data[i + 1] {
+            // stuffed 0xFF byte
+            0x00 => i += 2,
+            // restart markers are a part of entropy-coded data
+            marker if is_rst(marker) => i += 2,
+            // fill byte before a marker
+            0xFF => i += 1,
+            _ => return i,
+        }
+    }
+
+    return data.len();
+}
+
+// Tries to read a jpeg that starts exactly at start_index by walking its marker segments,
+// if valid jpeg bytes were found - returns exact positions of an image including EOI
 pub fn rip_jpeg(data: &[u8], start_index: usize) -> Option<Position> {
-    if data.len() < JPEG_IDENTIFIER.len() + JPEG_END_IDENTIFIER.len() ||
-        start_index + JPEG_IDENTIFIER.len() + JPEG_END_IDENTIFIER.len() > data.len() {
+    if start_index + JPEG_IDENTIFIER.len() > data.len() {
+        return None;
+    }
+
+    if data[start_index..start_index + JPEG_IDENTIFIER.len()] != JPEG_IDENTIFIER {
         return None;
     }
 
     let mut position: Position = Position{
-        start: usize::MAX,
+        start: start_index,
         end: usize::MAX,
         content_type: ContentType::JPEG,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
     };
 
-    for i in start_index..data.len() {
-        // start index
-        if i < data.len() - JPEG_IDENTIFIER.len() && position.start == usize::MAX {
-            if data[i..i + JPEG_IDENTIFIER.len()] == JPEG_IDENTIFIER {
-                position.start = i;
-            }
+    let mut found_sof: bool = false;
+    let mut found_sos: bool = false;
+    let mut is_first_marker: bool = true;
+
+    // right after SOI
+    let mut i: usize = start_index + 2;
+    while position.end == usize::MAX {
+        // skip fill bytes
+        while i < data.len() && data[i] == 0xFF && i + 1 < data.len() && data[i + 1] == 0xFF {
+            i += 1;
         }
 
-        // end index
-        if i <= data.len() - JPEG_END_IDENTIFIER.len() && position.end == usize::MAX {
-            if data[i..i + JPEG_END_IDENTIFIER.len()] == JPEG_END_IDENTIFIER {
-                position.end = i;
+        if i + 2 > data.len() {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        if data[i] != 0xFF {
+            // garbage where a marker should be
+            return None;
+        }
+
+        let marker: u8 = data[i + 1];
+        if is_first_marker {
+            // a real image starts with tables or application data,
+            // anything else most likely means SOI bytes were just a coincidence
+            if !(is_app(marker) || is_sof(marker) || marker == DQT || marker == DHT || marker == DRI || marker == COM) {
+                return None;
             }
+            is_first_marker = false;
         }
 
-        if position.start != usize::MAX && position.end != usize::MAX {
-            break;
+        match marker {
+            EOI => {
+                if !found_sof || !found_sos {
+                    // there was no actual image
+                    return None;
+                }
+                position.end = i + 2;
+            }
+
+            SOI | 0x00 => {
+                return None;
+            }
+
+            marker if is_rst(marker) || marker == TEM => {
+                // standalone markers without a length
+                i += 2;
+            }
+
+            _ => {
+                if i + 4 > data.len() {
+                    position.end = data.len();
+                    position.validity = Validity::TRUNCATED;
+                    break;
+                }
+
+                let segment_length: usize = u16::from_be_bytes([data[i + 2], data[i + 3]]) as usize;
+                if segment_length < 2 {
+                    return None;
+                }
+
+                if is_sof(marker) {
+                    // precision, height, width and component count
+                    if !found_sof && i + 10 <= data.len() {
+                        position.metadata.push(("width", u16::from_be_bytes([data[i + 7], data[i + 8]]).to_string()));
+                        position.metadata.push(("height", u16::from_be_bytes([data[i + 5], data[i + 6]]).to_string()));
+                        position.metadata.push(("components", data[i + 9].to_string()));
+                    }
+                    found_sof = true;
+                }
+
+                // jump over the segment
+                i += 2 + segment_length;
+                if i > data.len() {
+                    position.end = data.len();
+                    position.validity = Validity::TRUNCATED;
+                    break;
+                }
+
+                if marker == SOS {
+                    if !found_sof {
+                        return None;
+                    }
+                    found_sos = true;
+                    i = skip_entropy_coded_data(data, i);
+                }
+            }
         }
     }
 
-    if position.start == usize::MAX || position.end == usize::MAX || position.end < position.start {
-        return None;
+    return Some(position);
+}
+
+pub struct JpegRipper;
+
+impl Ripper for JpegRipper {
+    fn name(&self) -> &'static str {
+        return "JPEG";
     }
 
-    return Some(position);
+    fn content_type(&self) -> ContentType {
+        return ContentType::JPEG;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::IMG;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&JPEG_IDENTIFIER];
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_jpeg(data, start_index);
+    }
 }
\ No newline at end of file
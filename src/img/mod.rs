@@ -1,2 +1,4 @@
 pub mod png;
-pub mod jpeg;
\ No newline at end of file
+pub mod jpeg;
+pub mod gif;
+pub mod bmp;
\ No newline at end of file
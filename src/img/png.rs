@@ -18,47 +18,150 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::util::position::Position;
 use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+use crate::util::crc32::crc32;
 
 const PNG_IDENTIFIER: [u8; 8] = [0x89, 0x50, 0x4E, 0x47, 0xD, 0xA, 0x1A, 0xA];
-const PNG_END_IDENTIFIER: [u8; 8] = [0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82];
+const IHDR: [u8; 4] = [0x49, 0x48, 0x44, 0x52];
+const IEND: [u8; 4] = [0x49, 0x45, 0x4E, 0x44];
+const IHDR_DATA_LENGTH: usize = 13;
+// length + type before chunk data and crc after
+const CHUNK_OVERHEAD: usize = 12;
+const MAX_CHUNK_LENGTH: usize = 0x7FFFFFFF;
 
-// Reads data from specified start_index position,
-// if valid png bytes were found - returns exact positions of an image
+// Chunk types are made of ASCII letters only
+fn is_valid_chunk_type(chunk_type: &[u8]) -> bool {
+    return chunk_type.iter().all(|byte| byte.is_ascii_alphabetic());
+}
+
+// Checks whether there's something resembling a chunk header at chunk_start.
+// Gives the benefit of the doubt if the data ends before the header does
+fn looks_like_chunk(data: &[u8], chunk_start: usize) -> bool {
+    if chunk_start + 8 > data.len() {
+        return true;
+    }
+
+    let chunk_length: usize = u32::from_be_bytes([
+        data[chunk_start], data[chunk_start + 1], data[chunk_start + 2], data[chunk_start + 3]
+    ]) as usize;
+
+    return chunk_length <= MAX_CHUNK_LENGTH && is_valid_chunk_type(&data[chunk_start + 4..chunk_start + 8]);
+}
+
+// Tries to read a png that starts exactly at start_index by walking its chunks,
+// if valid png bytes were found - returns exact positions of an image.
+// Images with broken chunk CRCs are marked as corrupt, ones with missing
+// or overwritten chunks are cut at the last good chunk and marked as truncated
 pub fn rip_png(data: &[u8], start_index: usize) -> Option<Position> {
-    if data.len() < PNG_IDENTIFIER.len() + PNG_END_IDENTIFIER.len() ||
-        start_index + PNG_IDENTIFIER.len() + PNG_END_IDENTIFIER.len() > data.len() {
+    if start_index + PNG_IDENTIFIER.len() > data.len() {
+        return None;
+    }
+
+    if data[start_index..start_index + PNG_IDENTIFIER.len()] != PNG_IDENTIFIER {
         return None;
     }
 
     let mut position: Position = Position{
-        start: usize::MAX,
+        start: start_index,
         end: usize::MAX,
         content_type: ContentType::PNG,
+        validity: Validity::VALID,
+        parent: None,
+        metadata: Vec::new(),
     };
 
-    for i in start_index..data.len() {
-        // start index
-        if i < data.len() - PNG_IDENTIFIER.len() && position.start == usize::MAX {
-            if data[i..i + PNG_IDENTIFIER.len()] == PNG_IDENTIFIER {
-                position.start = i;
-            }
+    let mut chunk_start: usize = start_index + PNG_IDENTIFIER.len();
+    let mut is_first_chunk: bool = true;
+    while position.end == usize::MAX {
+        if chunk_start + 8 > data.len() {
+            // no end in sight, it might be further than the data goes
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
         }
 
-        // end index
-        if i <= data.len() - PNG_END_IDENTIFIER.len() && position.end == usize::MAX {
-            if data[i..i + PNG_END_IDENTIFIER.len()] == PNG_END_IDENTIFIER {
-                position.end = i + PNG_END_IDENTIFIER.len();
+        let chunk_length: usize = u32::from_be_bytes([
+            data[chunk_start], data[chunk_start + 1], data[chunk_start + 2], data[chunk_start + 3]
+        ]) as usize;
+        let chunk_type: &[u8] = &data[chunk_start + 4..chunk_start + 8];
+
+        if is_first_chunk {
+            // IHDR must come first
+            if chunk_type != IHDR || chunk_length != IHDR_DATA_LENGTH {
+                return None;
+            }
+            is_first_chunk = false;
+
+            let ihdr_start: usize = chunk_start + 8;
+            if ihdr_start + IHDR_DATA_LENGTH <= data.len() {
+                let ihdr: &[u8] = &data[ihdr_start..ihdr_start + IHDR_DATA_LENGTH];
+                position.metadata.push(("width", u32::from_be_bytes([ihdr[0], ihdr[1], ihdr[2], ihdr[3]]).to_string()));
+                position.metadata.push(("height", u32::from_be_bytes([ihdr[4], ihdr[5], ihdr[6], ihdr[7]]).to_string()));
+                position.metadata.push(("bit_depth", ihdr[8].to_string()));
+                position.metadata.push(("color_type", ihdr[9].to_string()));
             }
         }
 
-        if position.start != usize::MAX && position.end != usize::MAX {
+        if chunk_length > MAX_CHUNK_LENGTH || !is_valid_chunk_type(chunk_type) {
+            // this is not a chunk anymore, the image ends prematurely
+            position.end = chunk_start;
+            position.validity = Validity::TRUNCATED;
             break;
         }
-    }
 
-    if position.start == usize::MAX || position.end == usize::MAX || position.end <= position.start {
-        return None;
+        let chunk_end: usize = chunk_start + CHUNK_OVERHEAD + chunk_length;
+        if chunk_end > data.len() {
+            position.end = data.len();
+            position.validity = Validity::TRUNCATED;
+            break;
+        }
+
+        // crc covers chunk type and chunk data
+        let stored_crc: u32 = u32::from_be_bytes([
+            data[chunk_end - 4], data[chunk_end - 3], data[chunk_end - 2], data[chunk_end - 1]
+        ]);
+        if crc32(&data[chunk_start + 4..chunk_end - 4]) != stored_crc {
+            if chunk_type != IEND && !looks_like_chunk(data, chunk_end) {
+                // the chunk got cut off and something else follows
+                position.end = chunk_start;
+                position.validity = Validity::TRUNCATED;
+                break;
+            }
+            position.validity = Validity::CORRUPT;
+        }
+
+        if chunk_type == IEND {
+            position.end = chunk_end;
+        }
+
+        chunk_start = chunk_end;
     }
 
     return Some(position);
+}
+
+pub struct PngRipper;
+
+impl Ripper for PngRipper {
+    fn name(&self) -> &'static str {
+        return "PNG";
+    }
+
+    fn content_type(&self) -> ContentType {
+        return ContentType::PNG;
+    }
+
+    fn rip_type(&self) -> RipType {
+        return RipType::IMG;
+    }
+
+    fn magic(&self) -> &'static [&'static [u8]] {
+        return &[&PNG_IDENTIFIER];
+    }
+
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position> {
+        return rip_png(data, start_index);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,100 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+pub mod util;
+pub mod img;
+pub mod audio;
+pub mod container;
+pub mod compressed;
+pub mod registry;
+pub mod stream;
+pub mod manifest;
+pub mod nested;
+mod engine;
+
+pub use crate::util::position::Position;
+pub use crate::util::content_type::ContentType;
+pub use crate::util::validity::Validity;
+pub use crate::util::rip_type::RipType;
+pub use crate::util::overlap::OverlapPolicy;
+pub use crate::img::png::rip_png;
+pub use crate::img::jpeg::rip_jpeg;
+pub use crate::img::gif::rip_gif;
+pub use crate::img::bmp::rip_bmp;
+pub use crate::audio::mp3::rip_mp3;
+pub use crate::audio::ogg::rip_ogg;
+pub use crate::audio::flac::rip_flac;
+pub use crate::audio::aac::rip_aac;
+pub use crate::container::riff::rip_riff;
+pub use crate::container::isobmff::rip_isobmff;
+pub use crate::container::matroska::rip_matroska;
+pub use crate::container::zip::rip_zip;
+pub use crate::compressed::zlib::rip_zlib;
+pub use crate::compressed::gzip::rip_gzip;
+pub use crate::stream::{scan_stream, copy_position, hash_position};
+pub use crate::nested::{scan_compressed, NestedContent};
+
+use crate::util::overlap::resolve_overlaps;
+use crate::engine::Engine;
+use crate::audio::mp3::DEFAULT_MIN_FRAMES;
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+const DEFAULT_MAX_CONTENT_SIZE: usize = 1024 * 1024 * 1024;
+
+// What and how to look for
+#[derive(Debug, Clone, Copy)]
+pub struct ScanOptions {
+    pub rip_type: RipType,
+    pub overlap_policy: OverlapPolicy,
+    // how many bytes scan_stream examines at a time
+    pub chunk_size: usize,
+    // scan_stream won't read more than that to parse a single piece of content
+    pub max_content_size: usize,
+    // how many consecutive frames make an untagged audio stream
+    pub min_frames: usize,
+    // whether zlib and gzip streams are decompressed and looked into
+    pub decompress: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> ScanOptions {
+        return ScanOptions{
+            rip_type: RipType::ALL,
+            overlap_policy: OverlapPolicy::OUTERMOST,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_content_size: DEFAULT_MAX_CONTENT_SIZE,
+            min_frames: DEFAULT_MIN_FRAMES,
+            decompress: false,
+        };
+    }
+}
+
+// Finds all embedded content in data. Returned positions are sorted
+// by their start and have overlaps resolved as options say
+pub fn scan(data: &[u8], options: &ScanOptions) -> Vec<Position> {
+    let engine: Engine = Engine::new(options);
+
+    let mut skip_until: Vec<usize> = engine.new_skip_limits();
+    let positions: Vec<Position> = engine
+        .rip(data, data.len(), &mut skip_until)
+        .into_iter()
+        .map(|candidate| candidate.position)
+        .collect();
+
+    return resolve_overlaps(positions, options.overlap_policy);
+}
@@ -16,30 +16,195 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-mod util;
-mod img;
-mod audio;
-
 use std::path;
-use std::io::{Read, Write};
-use crate::util::position::Position;
-use crate::util::content_type::ContentType;
-use crate::img::png::rip_png;
-use crate::img::jpeg::rip_jpeg;
-use crate::audio::mp3::rip_mp3;
-
-#[derive(Debug)]
-enum RipType {
-    ALL,
-    IMG,
-    AUDIO,
+use std::collections::HashMap;
+use std::io::BufWriter;
+use rip::{scan_stream, copy_position, hash_position, ScanOptions, Position, ContentType, Validity, RipType, OverlapPolicy};
+use rip::registry::rippers_for;
+use rip::manifest::{Manifest, ManifestFormat, ManifestRecord};
+use rip::util::sha256::{Sha256, to_hex};
+use rip::util::walk::{walk_directory, PathFilter, WalkedFile};
+use rip::container::zip::{zip_members, extract_zip_member, ZipMember};
+use rip::nested::{scan_compressed, path_name, NestedContent};
+
+// Largest ZIP member that is extracted unless told otherwise, guards against archive bombs
+const DEFAULT_MAX_MEMBER_SIZE: usize = 1024 * 1024 * 1024;
+
+// Returns a comma separated list of formats that will be ripped
+fn format_names(rip_type: RipType) -> String {
+    let options: ScanOptions = ScanOptions{
+        rip_type: rip_type,
+        ..ScanOptions::default()
+    };
+    let names: Vec<&str> = rippers_for(&options).iter().map(|ripper| ripper.name()).collect();
+    return names.join(", ");
+}
+
+// Returns SHA-256 of the content at position, reports an error if it could not be read
+fn hash_content(source: &mut std::fs::File, source_path: &path::Path, position: &Position) -> Option<[u8; 32]> {
+    match hash_position(source, position) {
+        Ok(digest) => {
+            return Some(digest);
+        }
+        Err(error) => {
+            println!("[ERROR] Could not hash content of \"{}\": {}", source_path.display(), error);
+            return None;
+        }
+    }
+}
+
+fn hash_data(data: &[u8]) -> [u8; 32] {
+    let mut hasher: Sha256 = Sha256::new();
+    hasher.update(data);
+    return hasher.finalize();
+}
+
+// Compressed streams are written out only when archives are ripped, otherwise they are just looked into.
+// Bare zlib streams are not files, so they are never written out
+fn is_ripped(position: &Position, rip_type: RipType) -> bool {
+    if position.content_type == ContentType::ZLIB {
+        return false;
+    }
+    return !position.content_type.is_compressed() || rip_type == RipType::ALL || rip_type == RipType::ARCHIVE;
+}
+
+// Describes content the way listing shows it
+fn describe_position(label: &str, position: &Position) -> String {
+    return format!(
+        "[{}] {:?} {} (0x{:X}) - {} (0x{:X}), {} bytes, {}",
+        label,
+        position.content_type,
+        position.start,
+        position.start,
+        position.end,
+        position.end,
+        position.end - position.start,
+        position.validity.name()
+    );
+}
+
+// Where a piece of found content came from and what was done with it
+struct RecordedContent<'a> {
+    index: usize,
+    position: &'a Position,
+    decompressed_from: Option<&'a str>,
+    output_path: Option<&'a str>,
+    duplicate_of: Option<&'a str>,
+    digest: &'a [u8; 32],
+}
+
+// Adds a record about the content to the manifest
+fn record_to_manifest(manifest: &mut Manifest<BufWriter<std::fs::File>>, source_path: &path::Path, content: &RecordedContent) {
+    let record: ManifestRecord = ManifestRecord{
+        source_path: &source_path.to_string_lossy(),
+        index: content.index,
+        position: content.position,
+        decompressed_from: content.decompressed_from,
+        output_path: content.output_path,
+        duplicate_of: content.duplicate_of,
+        sha256: &to_hex(content.digest),
+    };
+    if let Err(error) = manifest.write_record(&record) {
+        println!("[ERROR] Could not write to the manifest: {}", error);
+    }
+}
+
+// Reads content at position into memory, reports an error if it could not be read
+fn read_content(source: &mut std::fs::File, source_path: &path::Path, position: &Position) -> Option<Vec<u8>> {
+    let mut content: Vec<u8> = Vec::with_capacity(position.end - position.start);
+    if let Err(error) = copy_position(source, position, &mut content) {
+        println!("[ERROR] Could not read content of \"{}\": {}", source_path.display(), error);
+        return None;
+    }
+    return Some(content);
+}
+
+// Reads members of a ZIP archive at position, reports an error if they could not be read
+fn read_zip_members(source: &mut std::fs::File, source_path: &path::Path, position: &Position) -> Option<(Vec<u8>, Vec<ZipMember>)> {
+    let archive: Vec<u8> = read_content(source, source_path, position)?;
+
+    match zip_members(&archive) {
+        Some(members) => {
+            return Some((archive, members));
+        }
+        None => {
+            println!("[ERROR] Could not read members of an archive at {} in \"{}\"", position.start, source_path.display());
+            return None;
+        }
+    }
+}
+
+// Writes out stored and deflated members of a ZIP archive at position under their names in directory
+fn extract_zip_members(
+    source: &mut std::fs::File,
+    source_path: &path::Path,
+    position: &Position,
+    directory: &path::Path,
+    max_member_size: usize,
+) {
+    let (archive, members): (Vec<u8>, Vec<ZipMember>) = match read_zip_members(source, source_path, position) {
+        Some(archive_and_members) => archive_and_members,
+        None => return,
+    };
+
+    for member in members.iter() {
+        let member_path: path::PathBuf = match member.relative_path() {
+            Some(relative_path) => directory.join(relative_path),
+            None => {
+                println!("[ERROR] Refusing to extract \"{}\": the name points outside of the archive's directory", member.name);
+                continue;
+            }
+        };
+
+        if member.is_directory() {
+            if let Err(error) = std::fs::create_dir_all(&member_path) {
+                println!("[ERROR] Could not create directory \"{}\": {}", member_path.display(), error);
+            }
+            continue;
+        }
+
+        let contents: Vec<u8>;
+        match extract_zip_member(&archive, member, max_member_size) {
+            Ok(member_contents) => {
+                contents = member_contents;
+            }
+            Err(error) => {
+                println!("[ERROR] Could not extract \"{}\" ({}): {}", member.name, member.method_name(), error.name());
+                continue;
+            }
+        }
+
+        if let Some(parent) = member_path.parent() {
+            if let Err(error) = std::fs::create_dir_all(parent) {
+                println!("[ERROR] Could not create directory \"{}\": {}", parent.display(), error);
+                continue;
+            }
+        }
+        if let Err(error) = std::fs::write(&member_path, &contents) {
+            println!("[ERROR] Error writing out the member \"{}\": {}", member_path.display(), error);
+            continue;
+        }
+
+        println!("[INFO] Extracted {} ({} bytes)", member_path.display(), contents.len());
+    }
 }
 
 fn main() {
     let mut save_directory: &path::Path = path::Path::new(".");
+    let mut specified_save_dir: Option<&path::Path> = None;
     let mut file_paths: Vec<&path::Path> = Vec::new();
     let mut max_file_size: u128 = u128::MAX;
+    let mut list_only: bool = false;
+    let mut manifest_path: Option<&path::Path> = None;
     let mut rip_type: RipType = RipType::ALL;
+    let mut overlap_policy: OverlapPolicy = OverlapPolicy::OUTERMOST;
+    let mut scan_options: ScanOptions = ScanOptions::default();
+    let mut recursive: bool = false;
+    let mut follow_symlinks: bool = false;
+    let mut path_filter: PathFilter = PathFilter::default();
+    let mut deduplicate: bool = false;
+    let mut zip_members_mode: bool = false;
+    let mut max_member_size: usize = DEFAULT_MAX_MEMBER_SIZE;
 
     // work out the arguments
     let args: Vec<String> = std::env::args().collect();
@@ -60,13 +225,38 @@ fn main() {
                 [FLAG]s\n\
                 \"-v\"   or \"--version\"               -> print version\n\
                 \"-h\"   or \"--help\"                  -> print this message\n\
+                \"-l\"   or \"--list\"                  -> only list found content without writing anything\n\
+                \"-r\"   or \"--recursive\"             -> go through directories and mirror their structure in the save directory\n\
+                \"-fs\"  or \"--follow-symlinks\"       -> follow symbolic links when going through directories\n\
+                \"-in\"  or \"--include\" [GLOB]        -> only examine files matching the pattern (can be repeated)\n\
+                \"-ex\"  or \"--exclude\" [GLOB]        -> do not examine files and directories matching the pattern (can be repeated)\n\
+                \"-dd\"  or \"--deduplicate\"           -> write identical content only once, across all files\n\
+                \"-zm\"  or \"--zip-members\"           -> list members of found ZIP archives, extract stored and deflated ones\n\
+                \"-dc\"  or \"--decompress\"            -> look for content inside of zlib and gzip streams as well\n\
+                \"-m\"   or \"--manifest\" [FILE]       -> write a record about each found content (CSV for *.csv, JSON Lines otherwise)\n\
                 \"-sd\"  or \"--save-dir\" [DIR]        -> specify save directory\n\
-                \"-mfs\" or \"--max-file-size\" [SIZE]  -> skip files bigger than size (in bytes)\n
+                \"-mfs\" or \"--max-file-size\" [SIZE]  -> skip files bigger than size (in bytes)\n\
+                \"-op\"  or \"--overlap-policy\" [POLICY] -> what to do with content found inside other content\n\
+                \"-cs\"  or \"--chunk-size\" [SIZE]     -> how many bytes to scan at a time (in bytes)\n\
+                \"-mcs\" or \"--max-content-size\" [SIZE] -> read no more than size bytes to rip a single file (in bytes)\n\
+                \"-mf\"  or \"--min-frames\" [COUNT]    -> how many consecutive frames make an untagged audio stream\n\
+                \"-mms\" or \"--max-member-size\" [SIZE] -> do not extract ZIP members bigger than size (in bytes, default 1 GiB)\n
+                \n\
+                [POLICY]\n\
+                OUTER -> (default) keep only the outermost content\n\
+                INNER -> keep only the innermost content\n\
+                ALL   -> keep everything, report what lies inside of what\n
+                \n\
+                [GLOB]\n\
+                *  -> any characters except for /, ** -> any characters, ? -> a single character, [abc] -> one of the characters\n\
+                Patterns without / are matched against file names, patterns with it - against paths relative to the given directory. Files given directly are always examined\n
                 \n\
                 [RIPTYPE]\n\
                 ALL   -> rip everything that seems like an embedded content\n\
                 IMG   -> try to look for images only\n\
-                AUDIO -> rip audio content"
+                AUDIO -> rip audio content\n\
+                VIDEO -> rip video content\n\
+                ARCHIVE -> rip archives"
             );
             return;
         }
@@ -77,37 +267,59 @@ fn main() {
             );
             return;
         }
-        else if &args[arg_index] == "-sd" || &args[arg_index] == "--save-dir" {
+        else if &args[arg_index] == "-l" || &args[arg_index] == "--list" {
+            list_only = true;
+        }
+        else if &args[arg_index] == "-r" || &args[arg_index] == "--recursive" {
+            recursive = true;
+        }
+        else if &args[arg_index] == "-fs" || &args[arg_index] == "--follow-symlinks" {
+            follow_symlinks = true;
+        }
+        else if &args[arg_index] == "-in" || &args[arg_index] == "--include" {
             if arg_index + 1 >= argc {
-                println!("[ERROR] Not enough arguments to set save directory and launch RIP");
+                println!("[ERROR] Not enough arguments to set include pattern and launch RIP");
                 return;
             }
 
-            arg_index += 1; // increment index no matter the further outcome
-            let specified_save_dir: &path::Path = path::Path::new(&args[arg_index]);
-            if !specified_save_dir.exists() {
-                // does not exist
-                match std::fs::create_dir_all(specified_save_dir) {
-                    Ok(()) => {
-                        save_directory = specified_save_dir;
-                    }
-
-                    Err(error) => {
-                        save_directory = &path::Path::new(".");
-                        println!("[ERROR] Error creating specified save directory: {}. Using working dir instead...", error);
-                    }
-                }
+            arg_index += 1;
+            path_filter.includes.push(args[arg_index].clone());
+        }
+        else if &args[arg_index] == "-ex" || &args[arg_index] == "--exclude" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set exclude pattern and launch RIP");
+                return;
             }
-            else if !specified_save_dir.is_dir() {
-                // it exists, but not a directory
-                println!("[ERROR] Specified save directory \"{}\" is NOT a directory. Using working dir instead...", specified_save_dir.display());
-                save_directory = &path::Path::new(".");
+
+            arg_index += 1;
+            path_filter.excludes.push(args[arg_index].clone());
+        }
+        else if &args[arg_index] == "-dd" || &args[arg_index] == "--deduplicate" {
+            deduplicate = true;
+        }
+        else if &args[arg_index] == "-zm" || &args[arg_index] == "--zip-members" {
+            zip_members_mode = true;
+        }
+        else if &args[arg_index] == "-dc" || &args[arg_index] == "--decompress" {
+            scan_options.decompress = true;
+        }
+        else if &args[arg_index] == "-m" || &args[arg_index] == "--manifest" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set manifest file and launch RIP");
+                return;
             }
-            else {
-                // exists and IS directory ! Everything's okay and easy
-                save_directory = specified_save_dir;
+
+            arg_index += 1;
+            manifest_path = Some(path::Path::new(&args[arg_index]));
+        }
+        else if &args[arg_index] == "-sd" || &args[arg_index] == "--save-dir" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set save directory and launch RIP");
+                return;
             }
 
+            arg_index += 1; // increment index no matter the further outcome
+            specified_save_dir = Some(path::Path::new(&args[arg_index]));
         }
         else if &args[arg_index] == "-mfs" || &args[arg_index] == "--max-file-size" {
             if arg_index + 1 >= argc {
@@ -127,17 +339,114 @@ fn main() {
                 }
             }
         }
+        else if &args[arg_index] == "-cs" || &args[arg_index] == "--chunk-size" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set chunk size and launch RIP");
+                return;
+            }
+
+            arg_index += 1;
+            match args[arg_index].parse::<usize>() {
+                Ok(chunk_size) if chunk_size > 0 => {
+                    scan_options.chunk_size = chunk_size;
+                }
+
+                _ => {
+                    println!("[ERROR] Invalid chunk size was specified");
+                    return;
+                }
+            }
+        }
+        else if &args[arg_index] == "-mcs" || &args[arg_index] == "--max-content-size" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set max content size and launch RIP");
+                return;
+            }
+
+            arg_index += 1;
+            match args[arg_index].parse::<usize>() {
+                Ok(max_csize) if max_csize > 0 => {
+                    scan_options.max_content_size = max_csize;
+                }
+
+                _ => {
+                    println!("[ERROR] Invalid max content size was specified");
+                    return;
+                }
+            }
+        }
+        else if &args[arg_index] == "-mms" || &args[arg_index] == "--max-member-size" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set max member size and launch RIP");
+                return;
+            }
+
+            arg_index += 1;
+            match args[arg_index].parse::<usize>() {
+                Ok(max_msize) => {
+                    max_member_size = max_msize;
+                }
+
+                Err(_) => {
+                    println!("[ERROR] Invalid max member size was specified");
+                    return;
+                }
+            }
+        }
+        else if &args[arg_index] == "-mf" || &args[arg_index] == "--min-frames" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set min frame count and launch RIP");
+                return;
+            }
+
+            arg_index += 1;
+            match args[arg_index].parse::<usize>() {
+                Ok(min_frames) if min_frames > 0 => {
+                    scan_options.min_frames = min_frames;
+                }
+
+                _ => {
+                    println!("[ERROR] Invalid min frame count was specified");
+                    return;
+                }
+            }
+        }
+        else if &args[arg_index] == "-op" || &args[arg_index] == "--overlap-policy" {
+            if arg_index + 1 >= argc {
+                println!("[ERROR] Not enough arguments to set overlap policy and launch RIP");
+                return;
+            }
+
+            arg_index += 1;
+            match args[arg_index].to_lowercase().as_str() {
+                "outer" => overlap_policy = OverlapPolicy::OUTERMOST,
+                "inner" => overlap_policy = OverlapPolicy::INNERMOST,
+                "all" => overlap_policy = OverlapPolicy::ALL,
+                _ => {
+                    println!("[ERROR] Invalid overlap policy was specified");
+                    return;
+                }
+            }
+        }
         else if file_paths.len() == 0 && &args[arg_index].to_lowercase() == "all" {
             rip_type = RipType::ALL;
-            println!("Ripping EVERYTHING (JPEG, PNG, MP3)");
+            println!("Ripping EVERYTHING ({})", format_names(rip_type));
         }
         else if file_paths.len() == 0 && &args[arg_index].to_lowercase() == "img" {
             rip_type = RipType::IMG;
-            println!("Ripping IMAGES (JPEG, PNG)");
+            println!("Ripping IMAGES ({})", format_names(rip_type));
         }
         else if file_paths.len() == 0 && &args[arg_index].to_lowercase() == "audio" {
             rip_type = RipType::AUDIO;
-            println!("Ripping AUDIO (MP3)");
+            println!("Ripping AUDIO ({})", format_names(rip_type));
+        }
+        else if file_paths.len() == 0 && &args[arg_index].to_lowercase() == "video" {
+            rip_type = RipType::VIDEO;
+            println!("Ripping VIDEO ({})", format_names(rip_type));
+        }
+        else if file_paths.len() == 0 && &args[arg_index].to_lowercase() == "archive" {
+            rip_type = RipType::ARCHIVE;
+            println!("Ripping ARCHIVES ({})", format_names(rip_type));
         }
         else {
             // that's a path to the file to be examined
@@ -147,15 +456,99 @@ fn main() {
         arg_index += 1;
     }
 
-    for file_path in file_paths {
-        print!("\n");
+    // nothing gets written when just listing
+    if let (Some(specified_save_dir), false) = (specified_save_dir, list_only) {
+        if !specified_save_dir.exists() {
+            // does not exist
+            match std::fs::create_dir_all(specified_save_dir) {
+                Ok(()) => {
+                    save_directory = specified_save_dir;
+                }
+
+                Err(error) => {
+                    println!("[ERROR] Error creating specified save directory: {}. Using working dir instead...", error);
+                }
+            }
+        }
+        else if !specified_save_dir.is_dir() {
+            // it exists, but not a directory
+            println!("[ERROR] Specified save directory \"{}\" is NOT a directory. Using working dir instead...", specified_save_dir.display());
+        }
+        else {
+            // exists and IS directory ! Everything's okay and easy
+            save_directory = specified_save_dir;
+        }
+    }
+
+    let mut manifest: Option<Manifest<BufWriter<std::fs::File>>> = None;
+    if let Some(manifest_path) = manifest_path {
+        let manifest_file: std::fs::File;
+        match std::fs::File::create(manifest_path) {
+            Ok(f) => {
+                manifest_file = f;
+            }
+            Err(error) => {
+                println!("[ERROR] Could not create manifest \"{}\": {}", manifest_path.display(), error);
+                return;
+            }
+        }
 
+        match Manifest::new(BufWriter::new(manifest_file), ManifestFormat::from_path(manifest_path)) {
+            Ok(m) => {
+                manifest = Some(m);
+            }
+            Err(error) => {
+                println!("[ERROR] Could not write to the manifest \"{}\": {}", manifest_path.display(), error);
+                return;
+            }
+        }
+    }
+
+    scan_options.rip_type = rip_type;
+    scan_options.overlap_policy = overlap_policy;
+
+    // hashes of content that was written out and where it was written to
+    let mut written_contents: HashMap<[u8; 32], String> = HashMap::new();
+
+    // work out which files to examine and which subdirectory of the save directory their content goes to
+    let mut input_files: Vec<(path::PathBuf, path::PathBuf)> = Vec::new();
+    for file_path in file_paths {
         if !file_path.exists() {
             // does not exist
             println!("[ERROR] \"{}\" does not exist", file_path.display());
             continue;
         }
 
+        if !file_path.is_dir() {
+            // files named explicitly are always examined, patterns only narrow down directory walks
+            input_files.push((file_path.to_path_buf(), path::PathBuf::new()));
+            continue;
+        }
+
+        if !recursive {
+            println!("[INFO] Skipping directory \"{}\" (use -r to go through it)...", file_path.display());
+            continue;
+        }
+
+        let mut walk_errors: Vec<(path::PathBuf, std::io::Error)> = Vec::new();
+        let walked_files: Vec<WalkedFile> = walk_directory(file_path, follow_symlinks, &path_filter, &mut walk_errors);
+        for (error_path, error) in walk_errors {
+            println!("[ERROR] Could not go through \"{}\": {}", error_path.display(), error);
+        }
+
+        for walked_file in walked_files {
+            let relative_dir: path::PathBuf = match walked_file.relative_path.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => path::PathBuf::new(),
+            };
+            input_files.push((walked_file.path, relative_dir));
+        }
+    }
+
+    for (file_path, relative_dir) in input_files.iter() {
+        let file_path: &path::Path = file_path;
+        println!();
+
         // get file's metadata
         let file_metadata: std::fs::Metadata;
         match std::fs::metadata(file_path) {
@@ -169,12 +562,6 @@ fn main() {
             }
         }
 
-        // skip directories
-        if file_metadata.is_dir() {
-            println!("[INFO] Skipping directory \"{}\"...", file_path.display());
-            continue;
-        }
-
         // check if the file size is allowed
         if (file_metadata.len() as u128) > max_file_size {
             println!("[INFO] \"{}\" exceeds maximum file size. Skipping...", file_path.display());
@@ -183,8 +570,6 @@ fn main() {
 
         println!("[INFO] Working with \"{}\"...", file_path.display());
 
-        let mut file_contents: Vec<u8> = Vec::with_capacity(file_metadata.len() as usize);
-
         // open file
         let mut file_handle: std::fs::File;
         match std::fs::File::open(file_path) {
@@ -197,119 +582,80 @@ fn main() {
             }
         }
 
-        // load into memory
-        match file_handle.read_to_end(&mut file_contents) {
-            Ok(_) => {}
+        // look for content chunk by chunk
+        let positions: Vec<Position>;
+        match scan_stream(&mut file_handle, &scan_options) {
+            Ok(found_positions) => {
+                positions = found_positions;
+            }
             Err(error) => {
                 println!("[ERROR] Error reading \"{}\": {}", file_path.display(), error);
+                continue;
             }
         }
 
-        // keep track of found content
-        let mut positions: Vec<Position> = Vec::new();
+        if positions.len() == 0 {
+            println!("[INFO] Didn't find anything");
+            continue;
+        }
 
-        match rip_type {
-            RipType::IMG => {
-                // find PNG positions
-                let mut cursor_index: usize = 0;
-                while (cursor_index as u64) < file_metadata.len() {
-                    match rip_png(&file_contents, cursor_index) {
-                        Some(pos) => {
-                            cursor_index = pos.end;
-                            positions.push(pos);
-                        }
-                        None => {
-                            // no PNGs were found
-                            break;
-                        }
-                    }
+        if list_only {
+            for (position_index, position) in positions.iter().enumerate() {
+                let mut line: String = describe_position(&position_index.to_string(), position);
+                if let Some(parent_index) = position.parent {
+                    line += &format!(", inside [{}]", parent_index);
                 }
+                println!("{}", line);
 
-                // find JPEG positions
-                cursor_index = 0;
-                while (cursor_index as u64) < file_metadata.len() {
-                    match rip_jpeg(&file_contents, cursor_index) {
-                        Some(pos) => {
-                            cursor_index = pos.end;
-                            positions.push(pos);
-                        }
-                        None => {
-                            // no JPEGs were found
-                            break;
+                if zip_members_mode && position.content_type == ContentType::ZIP && position.validity != Validity::TRUNCATED {
+                    if let Some((_, members)) = read_zip_members(&mut file_handle, file_path, position) {
+                        for member in members.iter() {
+                            println!("    {}, {} bytes, {}", member.name, member.uncompressed_size, member.method_name());
                         }
                     }
                 }
-            }
 
-            RipType::AUDIO => {
-                // find MP3 positions
-                let mut cursor_index = 0;
-                while (cursor_index as u64) < file_metadata.len() {
-                    match rip_mp3(&file_contents, cursor_index) {
-                        Some(pos) => {
-                            cursor_index = pos.end;
-                            positions.push(pos);
-                        }
-                        None => {
-                            // no MP3s were found
-                            break;
-                        }
+                if let Some(manifest) = manifest.as_mut() {
+                    if let Some(digest) = hash_content(&mut file_handle, file_path, position) {
+                        record_to_manifest(manifest, file_path, &RecordedContent{
+                            index: position_index,
+                            position: position,
+                            decompressed_from: None,
+                            output_path: None,
+                            duplicate_of: None,
+                            digest: &digest,
+                        });
                     }
                 }
 
-            }
-
-            RipType::ALL => {
-                // find PNG positions
-                let mut cursor_index: usize = 0;
-                while (cursor_index as u64) < file_metadata.len() {
-                    match rip_png(&file_contents, cursor_index) {
-                        Some(pos) => {
-                            cursor_index = pos.end;
-                            positions.push(pos);
-                        }
-                        None => {
-                            // no PNGs were found
-                            break;
-                        }
-                    }
+                if !scan_options.decompress || !position.content_type.is_compressed() {
+                    continue;
                 }
-
-                // find JPEG positions
-                cursor_index = 0;
-                while (cursor_index as u64) < file_metadata.len() {
-                    match rip_jpeg(&file_contents, cursor_index) {
-                        Some(pos) => {
-                            cursor_index = pos.end;
-                            positions.push(pos);
-                        }
-                        None => {
-                            // no JPEGs were found
-                            break;
-                        }
+                let content: Vec<u8> = match read_content(&mut file_handle, file_path, position) {
+                    Some(content) => content,
+                    None => continue,
+                };
+                for nested in scan_compressed(&content, position.content_type, &[position_index], &scan_options) {
+                    let stream_path: String = path_name(&nested.path[..nested.path.len() - 1]);
+                    let mut line: String = describe_position(&path_name(&nested.path), &nested.position);
+                    if let Some(parent_index) = nested.position.parent {
+                        line += &format!(", inside [{}/{}]", stream_path, parent_index);
                     }
-                }
-
-                // find MP3 positions
-                cursor_index = 0;
-                while (cursor_index as u64) < file_metadata.len() {
-                    match rip_mp3(&file_contents, cursor_index) {
-                        Some(pos) => {
-                            cursor_index = pos.end;
-                            positions.push(pos);
-                        }
-                        None => {
-                            // no MP3s were found
-                            break;
-                        }
+                    line += &format!(", decompressed from [{}]", stream_path);
+                    println!("{}", line);
+
+                    if let Some(manifest) = manifest.as_mut() {
+                        record_to_manifest(manifest, file_path, &RecordedContent{
+                            index: nested.path[nested.path.len() - 1],
+                            position: &nested.position,
+                            decompressed_from: Some(&stream_path),
+                            output_path: None,
+                            duplicate_of: None,
+                            digest: &hash_data(&nested.data),
+                        });
                     }
                 }
             }
-        }
-
-
-        if positions.len() == 0 {
-            println!("[INFO] Didn't find anything");
             continue;
         }
 
@@ -325,51 +671,243 @@ fn main() {
             }
         }
 
-        // (TODO) work out overlaps
-        // and save found files to the disk
+        // mirror the source's place in the walked directory
+        let output_directory: path::PathBuf = save_directory.join(relative_dir);
+        if let Err(error) = std::fs::create_dir_all(&output_directory) {
+            println!("[ERROR] Could not create output directory \"{}\": {}", output_directory.display(), error);
+            continue;
+        }
+
+        // save found files to the disk
+        let output_file_paths: Vec<String> = positions
+            .iter()
+            .enumerate()
+            .map(|(position_index, position)| format!(
+                "{}_{}.{}",
+                output_directory.join(&source_file_name).to_string_lossy(),
+                position_index,
+                position.content_type.extension()
+            ))
+            .collect();
+
+        // where each content actually is, duplicates point to the already written copy
+        let mut content_paths: Vec<String> = output_file_paths.clone();
+
         for position_index in 0..positions.len() {
-            // create file
-            let mut output_file_path_string: String = save_directory.join(&source_file_name).to_string_lossy().to_string();
-            match positions[position_index].content_type {
-                ContentType::PNG => {
-                    output_file_path_string = 
-                        output_file_path_string + &format!("_{}.png", position_index);
+            let position: &Position = &positions[position_index];
+            let output_file_path_string: &String = &output_file_paths[position_index];
+
+            if !is_ripped(position, rip_type) {
+                content_paths[position_index] = format!("{:?} at {} (0x{:X})", position.content_type, position.start, position.start);
+
+                // content found inside of it refers to it in the manifest
+                if let Some(manifest) = manifest.as_mut() {
+                    if let Some(digest) = hash_content(&mut file_handle, file_path, position) {
+                        record_to_manifest(manifest, file_path, &RecordedContent{
+                            index: position_index,
+                            position: position,
+                            decompressed_from: None,
+                            output_path: None,
+                            duplicate_of: None,
+                            digest: &digest,
+                        });
+                    }
                 }
+                continue;
+            }
 
-                ContentType::JPEG => {
-                    output_file_path_string =
-                        output_file_path_string + &format!("_{}.jpeg", position_index);
+            // the hash is needed both to find duplicates and to describe content in the manifest
+            let mut digest: Option<[u8; 32]> = None;
+            if deduplicate || manifest.is_some() {
+                digest = hash_content(&mut file_handle, file_path, position);
+                if digest.is_none() {
+                    continue;
                 }
+            }
 
-                ContentType::MP3 => {
-                    output_file_path_string =
-                        output_file_path_string + &format!("_{}.mp3", position_index);
-                }
+            let mut duplicate_of: Option<String> = None;
+            if let (true, Some(digest)) = (deduplicate, digest) {
+                duplicate_of = written_contents.get(&digest).cloned();
             }
 
-            let mut output_file_handle: std::fs::File;
-            match std::fs::File::create(&output_file_path_string) {
-                Ok(f) => {
-                    output_file_handle = f;
+            if duplicate_of.is_none() {
+                // create file
+                let mut output_file_handle: std::fs::File;
+                match std::fs::File::create(output_file_path_string) {
+                    Ok(f) => {
+                        output_file_handle = f;
+                    }
+                    Err(error) => {
+                        println!("[ERROR] Could not create output file \"{}\": {}", output_file_path_string, error);
+                        continue;
+                    }
                 }
-                Err(error) => {
-                    println!("[ERROR] Could not create output file \"{}\": {}", output_file_path_string, error);
+
+                // copy contents straight from the source file
+                if let Err(error) = copy_position(&mut file_handle, position, &mut output_file_handle) {
+                    println!("[ERROR] Error writing out the output file \"{}\": {}", output_file_path_string, error);
                     continue;
                 }
+
+                if let (true, Some(digest)) = (deduplicate, digest) {
+                    written_contents.insert(digest, output_file_path_string.clone());
+                }
             }
 
-            // write contents
-            match output_file_handle.write(&file_contents[positions[position_index].start..positions[position_index].end]) {
-                Ok(_) => {}
-                Err(error) => {
-                    println!("[ERROR] Error writing out the output file \"{}\": {}", output_file_path_string, error);
+            if let (Some(manifest), Some(digest)) = (manifest.as_mut(), digest) {
+                let output_path: Option<&str> = match duplicate_of {
+                    Some(_) => None,
+                    None => Some(output_file_path_string),
+                };
+                record_to_manifest(manifest, file_path, &RecordedContent{
+                    index: position_index,
+                    position: position,
+                    decompressed_from: None,
+                    output_path: output_path,
+                    duplicate_of: duplicate_of.as_deref(),
+                    digest: &digest,
+                });
+            }
+
+            let mut notes: Vec<String> = vec![
+                format!("{} bytes", position.end - position.start)
+            ];
+            if position.validity != Validity::VALID {
+                notes.push(String::from(position.validity.name()));
+            }
+            if let Some(parent_index) = position.parent {
+                notes.push(format!("inside {}", content_paths[parent_index]));
+            }
+
+            match duplicate_of {
+                Some(original_path) => {
+                    println!("[INFO] Skipped a duplicate of {} ({})", original_path, notes.join(", "));
+                    content_paths[position_index] = original_path;
+                }
+                None => {
+                    println!("[INFO] Outputted {} ({})", output_file_path_string, notes.join(", "));
+
+                    // truncated archives have no central directory to read members from
+                    if zip_members_mode && position.content_type == ContentType::ZIP && position.validity != Validity::TRUNCATED {
+                        // members go next to the archive, into a directory named after it
+                        let members_directory: path::PathBuf = output_directory.join(format!("{}_{}", source_file_name, position_index));
+                        extract_zip_members(&mut file_handle, file_path, position, &members_directory, max_member_size);
+                    }
                 }
             }
+        }
 
-            println!("[INFO] Outputted {} ({} bytes)",
-                output_file_path_string,
-                positions[position_index].end - positions[position_index].start
-            );
+        if !scan_options.decompress {
+            continue;
+        }
+
+        // what's inside of compressed streams goes next to the rest, named after the path to it
+        for (position_index, position) in positions.iter().enumerate() {
+            if !position.content_type.is_compressed() {
+                continue;
+            }
+            let content: Vec<u8> = match read_content(&mut file_handle, file_path, position) {
+                Some(content) => content,
+                None => continue,
+            };
+
+            // where content of each path actually is
+            let mut nested_content_paths: HashMap<Vec<usize>, String> = HashMap::new();
+            nested_content_paths.insert(vec![position_index], content_paths[position_index].clone());
+
+            for nested in scan_compressed(&content, position.content_type, &[position_index], &scan_options) {
+                let NestedContent{path: nested_path, position: nested_position, data: nested_data} = nested;
+                let stream_path: &[usize] = &nested_path[..nested_path.len() - 1];
+                let stream_content_path: String = nested_content_paths.get(stream_path).cloned().unwrap_or_default();
+
+                if !is_ripped(&nested_position, rip_type) {
+                    nested_content_paths.insert(
+                        nested_path.clone(),
+                        format!("{:?} at {} (0x{:X}) of {}", nested_position.content_type, nested_position.start, nested_position.start, stream_content_path)
+                    );
+
+                    if let Some(manifest) = manifest.as_mut() {
+                        record_to_manifest(manifest, file_path, &RecordedContent{
+                            index: nested_path[nested_path.len() - 1],
+                            position: &nested_position,
+                            decompressed_from: Some(&path_name(stream_path)),
+                            output_path: None,
+                            duplicate_of: None,
+                            digest: &hash_data(&nested_data),
+                        });
+                    }
+                    continue;
+                }
+
+                let nested_indices: Vec<String> = nested_path.iter().map(|index| index.to_string()).collect();
+                let output_file_path_string: String = format!(
+                    "{}_{}.{}",
+                    output_directory.join(&source_file_name).to_string_lossy(),
+                    nested_indices.join("_"),
+                    nested_position.content_type.extension()
+                );
+
+                let digest: [u8; 32] = hash_data(&nested_data);
+                let mut duplicate_of: Option<String> = None;
+                if deduplicate {
+                    duplicate_of = written_contents.get(&digest).cloned();
+                }
+
+                if duplicate_of.is_none() {
+                    if let Err(error) = std::fs::write(&output_file_path_string, &nested_data) {
+                        println!("[ERROR] Error writing out the output file \"{}\": {}", output_file_path_string, error);
+                        continue;
+                    }
+                    if deduplicate {
+                        written_contents.insert(digest, output_file_path_string.clone());
+                    }
+                }
+
+                if let Some(manifest) = manifest.as_mut() {
+                    let output_path: Option<&str> = match duplicate_of {
+                        Some(_) => None,
+                        None => Some(&output_file_path_string),
+                    };
+                    record_to_manifest(manifest, file_path, &RecordedContent{
+                        index: nested_path[nested_path.len() - 1],
+                        position: &nested_position,
+                        decompressed_from: Some(&path_name(stream_path)),
+                        output_path: output_path,
+                        duplicate_of: duplicate_of.as_deref(),
+                        digest: &digest,
+                    });
+                }
+
+                let mut notes: Vec<String> = vec![
+                    format!("{} bytes", nested_position.end - nested_position.start)
+                ];
+                if nested_position.validity != Validity::VALID {
+                    notes.push(String::from(nested_position.validity.name()));
+                }
+                if let Some(parent_index) = nested_position.parent {
+                    let mut parent_path: Vec<usize> = stream_path.to_vec();
+                    parent_path.push(parent_index);
+                    notes.push(format!("inside {}", nested_content_paths.get(&parent_path).cloned().unwrap_or_default()));
+                }
+                notes.push(format!("decompressed from {}", stream_content_path));
+
+                match duplicate_of {
+                    Some(original_path) => {
+                        println!("[INFO] Skipped a duplicate of {} ({})", original_path, notes.join(", "));
+                        nested_content_paths.insert(nested_path, original_path);
+                    }
+                    None => {
+                        println!("[INFO] Outputted {} ({})", output_file_path_string, notes.join(", "));
+                        nested_content_paths.insert(nested_path, output_file_path_string);
+                    }
+                }
+            }
         }
     }
-}
+
+    if let Some(manifest) = manifest.as_mut() {
+        if let Err(error) = manifest.flush() {
+            println!("[ERROR] Could not write to the manifest: {}", error);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,165 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::io::{self, Write};
+use std::path::Path;
+use crate::util::position::Position;
+
+const CSV_HEADER: &str = "source,index,start,end,length,content_type,validity,parent,decompressed_from,output,duplicate_of,sha256,metadata";
+
+// How manifest records are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    // one JSON object per line
+    JSONL,
+    // comma separated values with a header line
+    CSV,
+}
+
+impl ManifestFormat {
+    // CSV for *.csv files, JSON Lines for everything else
+    pub fn from_path(path: &Path) -> ManifestFormat {
+        match path.extension() {
+            Some(extension) if extension.to_string_lossy().to_lowercase() == "csv" => return ManifestFormat::CSV,
+            _ => return ManifestFormat::JSONL,
+        }
+    }
+}
+
+// Everything known about a single piece of ripped content
+pub struct ManifestRecord<'a> {
+    pub source_path: &'a str,
+    // index of the position among ones found in the same source
+    pub index: usize,
+    pub position: &'a Position,
+    // path of the compressed stream the content was found in after decompressing it,
+    // offsets are in its decompressed data then
+    pub decompressed_from: Option<&'a str>,
+    // where the content was written to, None if it was not
+    pub output_path: Option<&'a str>,
+    // where the identical content was already written to, if it was not written again
+    pub duplicate_of: Option<&'a str>,
+    // hex SHA-256 of the content
+    pub sha256: &'a str,
+}
+
+// Writes structured records about ripped content
+pub struct Manifest<W: Write> {
+    writer: W,
+    format: ManifestFormat,
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped: String = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    return escaped;
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        return format!("\"{}\"", value.replace('"', "\"\""));
+    }
+    return String::from(value);
+}
+
+impl<W: Write> Manifest<W> {
+    pub fn new(mut writer: W, format: ManifestFormat) -> io::Result<Manifest<W>> {
+        if format == ManifestFormat::CSV {
+            writeln!(writer, "{}", CSV_HEADER)?;
+        }
+
+        return Ok(Manifest{
+            writer: writer,
+            format: format,
+        });
+    }
+
+    pub fn write_record(&mut self, record: &ManifestRecord) -> io::Result<()> {
+        let position: &Position = record.position;
+
+        match self.format {
+            ManifestFormat::JSONL => {
+                let metadata: Vec<String> = position.metadata
+                    .iter()
+                    .map(|(key, value)| format!("{}:{}", escape_json(key), escape_json(value)))
+                    .collect();
+
+                writeln!(
+                    self.writer,
+                    "{{\"source\":{},\"index\":{},\"start\":{},\"end\":{},\"length\":{},\"content_type\":{},\"validity\":{},\"parent\":{},\"decompressed_from\":{},\"output\":{},\"duplicate_of\":{},\"sha256\":{},\"metadata\":{{{}}}}}",
+                    escape_json(record.source_path),
+                    record.index,
+                    position.start,
+                    position.end,
+                    position.end - position.start,
+                    escape_json(&format!("{:?}", position.content_type)),
+                    escape_json(position.validity.name()),
+                    position.parent.map_or(String::from("null"), |parent| parent.to_string()),
+                    record.decompressed_from.map_or(String::from("null"), escape_json),
+                    record.output_path.map_or(String::from("null"), escape_json),
+                    record.duplicate_of.map_or(String::from("null"), escape_json),
+                    escape_json(record.sha256),
+                    metadata.join(",")
+                )?;
+            }
+
+            ManifestFormat::CSV => {
+                let metadata: Vec<String> = position.metadata
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, value))
+                    .collect();
+
+                writeln!(
+                    self.writer,
+                    "{},{},{},{},{},{:?},{},{},{},{},{},{},{}",
+                    escape_csv(record.source_path),
+                    record.index,
+                    position.start,
+                    position.end,
+                    position.end - position.start,
+                    position.content_type,
+                    position.validity.name(),
+                    position.parent.map_or(String::new(), |parent| parent.to_string()),
+                    record.decompressed_from.unwrap_or(""),
+                    escape_csv(record.output_path.unwrap_or("")),
+                    escape_csv(record.duplicate_of.unwrap_or("")),
+                    record.sha256,
+                    escape_csv(&metadata.join(";"))
+                )?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        return self.writer.flush();
+    }
+}
@@ -0,0 +1,99 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::compressed::gzip::decompress_gzip;
+use crate::compressed::zlib::decompress_zlib;
+use crate::{scan, ScanOptions};
+
+// compressed streams inside of compressed streams are not followed any deeper
+const MAX_NESTING_DEPTH: usize = 8;
+
+// Content found in decompressed data of a compressed stream
+#[derive(Debug, Clone)]
+pub struct NestedContent {
+    // indices leading to the content: the first one is of a compressed stream among content found
+    // in the source, every next one is among content found in decompressed data of the previous one
+    pub path: Vec<usize>,
+    // offsets are in decompressed data of the stream the content was found in,
+    // parent is an index among content found in the same data
+    pub position: Position,
+    pub data: Vec<u8>,
+}
+
+// Returns path as it is shown to people, like "3/0/1"
+pub fn path_name(path: &[usize]) -> String {
+    let indices: Vec<String> = path.iter().map(|index| index.to_string()).collect();
+    return indices.join("/");
+}
+
+// Returns decompressed data of compressed content, refusing to produce more than max_length bytes
+pub fn decompress(content: &[u8], content_type: ContentType, max_length: usize) -> Option<Vec<u8>> {
+    match content_type {
+        ContentType::GZIP => return decompress_gzip(content, max_length),
+        ContentType::ZLIB => return decompress_zlib(content, max_length),
+        _ => return None,
+    }
+}
+
+// Decompresses content of a compressed stream at path in memory and finds everything inside,
+// going into compressed streams found there as well. Everything decompressed on the way, the stream
+// itself and streams inside of it, takes no more than max_content_size altogether.
+// Content comes right after the stream it was found in
+pub fn scan_compressed(content: &[u8], content_type: ContentType, path: &[usize], options: &ScanOptions) -> Vec<NestedContent> {
+    let mut found: Vec<NestedContent> = Vec::new();
+    let mut budget: usize = options.max_content_size;
+    scan_compressed_within(content, content_type, path, options, &mut budget, &mut found);
+    return found;
+}
+
+// Does the work of scan_compressed, budget is how many bytes are still allowed to be decompressed
+fn scan_compressed_within(
+    content: &[u8],
+    content_type: ContentType,
+    path: &[usize],
+    options: &ScanOptions,
+    budget: &mut usize,
+    found: &mut Vec<NestedContent>,
+) {
+    let decompressed: Vec<u8> = match decompress(content, content_type, *budget) {
+        Some(decompressed) => decompressed,
+        None => return,
+    };
+    *budget -= decompressed.len();
+
+    for (index, position) in scan(&decompressed, options).into_iter().enumerate() {
+        let mut nested_path: Vec<usize> = path.to_vec();
+        nested_path.push(index);
+
+        let nested: NestedContent = NestedContent{
+            path: nested_path,
+            data: decompressed[position.start..position.end].to_vec(),
+            position: position,
+        };
+
+        let mut inner: Vec<NestedContent> = Vec::new();
+        if nested.position.content_type.is_compressed() && nested.path.len() < MAX_NESTING_DEPTH && *budget > 0 {
+            scan_compressed_within(&nested.data, nested.position.content_type, &nested.path, options, budget, &mut inner);
+        }
+
+        found.push(nested);
+        found.extend(inner);
+    }
+}
@@ -0,0 +1,80 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::ripper::Ripper;
+use crate::util::rip_type::RipType;
+use crate::ScanOptions;
+use crate::img::png::PngRipper;
+use crate::img::jpeg::JpegRipper;
+use crate::img::gif::GifRipper;
+use crate::img::bmp::BmpRipper;
+use crate::audio::mp3::Mp3Ripper;
+use crate::audio::ogg::OggRipper;
+use crate::audio::flac::FlacRipper;
+use crate::audio::aac::AacRipper;
+use crate::container::riff::RiffRipper;
+use crate::container::isobmff::IsoBmffRipper;
+use crate::container::matroska::MatroskaRipper;
+use crate::container::zip::ZipRipper;
+use crate::compressed::gzip::GzipRipper;
+use crate::compressed::zlib::ZlibRipper;
+
+// Returns every known ripper set up according to options
+pub fn all_rippers(options: &ScanOptions) -> Vec<Box<dyn Ripper>> {
+    let mut rippers: Vec<Box<dyn Ripper>> = vec![
+        Box::new(PngRipper),
+        Box::new(JpegRipper),
+        Box::new(GifRipper),
+        Box::new(BmpRipper),
+        Box::new(IsoBmffRipper{rip_type: RipType::IMG}),
+        Box::new(Mp3Ripper{min_frames: options.min_frames}),
+        Box::new(OggRipper),
+        Box::new(FlacRipper),
+        Box::new(AacRipper{min_frames: options.min_frames}),
+        Box::new(IsoBmffRipper{rip_type: RipType::AUDIO}),
+        Box::new(IsoBmffRipper{rip_type: RipType::VIDEO}),
+        Box::new(MatroskaRipper),
+        Box::new(ZipRipper),
+        Box::new(GzipRipper{max_decompressed_size: options.max_content_size}),
+    ];
+
+    // RIFF headers are read once no matter how many of their form types are wanted
+    if RiffRipper::carves(options.rip_type) {
+        rippers.push(Box::new(RiffRipper{rip_type: options.rip_type}));
+    }
+
+    // bare zlib streams are not files, they are only worth finding to look inside of them
+    if options.decompress {
+        rippers.push(Box::new(ZlibRipper{max_decompressed_size: options.max_content_size}));
+    }
+
+    return rippers;
+}
+
+// Returns rippers that produce content of the type options ask for,
+// along with the ones for compressed streams if those are to be looked into
+pub fn rippers_for(options: &ScanOptions) -> Vec<Box<dyn Ripper>> {
+    return all_rippers(options)
+        .into_iter()
+        .filter(|ripper| {
+            options.rip_type == RipType::ALL
+                || ripper.rip_type() == options.rip_type
+                || (options.decompress && ripper.content_type().is_compressed())
+        })
+        .collect();
+}
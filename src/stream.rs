@@ -0,0 +1,156 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use crate::util::position::Position;
+use crate::util::overlap::resolve_overlaps;
+use crate::util::sha256::Sha256;
+use crate::engine::{Engine, Candidate};
+use crate::ScanOptions;
+
+// Content is only recognised if enough of it is seen at once, chained frame
+// formats need several frames after the signature, so every window holds
+// at least that much past its chunk
+const MIN_LOOKAHEAD: usize = 64 * 1024;
+
+// Reads up to length bytes of source starting at offset into buffer
+fn read_window<R: Read + Seek>(source: &mut R, offset: u64, length: usize, buffer: &mut Vec<u8>) -> io::Result<()> {
+    buffer.clear();
+    source.seek(SeekFrom::Start(offset))?;
+    source.take(length as u64).read_to_end(buffer)?;
+    return Ok(());
+}
+
+// Finds all embedded content in source without loading it into memory as a whole.
+// Source is scanned chunk by chunk, each window additionally holds the next chunk (or more),
+// so signatures and content crossing chunk boundaries are not lost. Content that
+// goes on past the window is parsed again with more data, up to max_content_size.
+// Returned positions are absolute offsets in source with overlaps resolved
+pub fn scan_stream<R: Read + Seek>(source: &mut R, options: &ScanOptions) -> io::Result<Vec<Position>> {
+    let engine: Engine = Engine::new(options);
+    let source_length: u64 = source.seek(SeekFrom::End(0))?;
+
+    let chunk_size: usize = std::cmp::max(options.chunk_size, engine.max_signature_length());
+    let window_size: usize = chunk_size.saturating_add(std::cmp::max(chunk_size, MIN_LOOKAHEAD));
+
+    let mut window: Vec<u8> = Vec::new();
+    let mut extended_window: Vec<u8> = Vec::new();
+    let mut positions: Vec<Position> = Vec::new();
+    // absolute offsets
+    let mut skip_until: Vec<usize> = engine.new_skip_limits();
+
+    let mut chunk_start: u64 = 0;
+    while chunk_start < source_length {
+        read_window(source, chunk_start, window_size, &mut window)?;
+        let window_is_last: bool = chunk_start + window.len() as u64 >= source_length;
+
+        let mut window_skip_until: Vec<usize> = skip_until
+            .iter()
+            .map(|limit| limit.saturating_sub(chunk_start as usize))
+            .collect();
+
+        for candidate in engine.rip(&window, chunk_size, &mut window_skip_until) {
+            let Candidate{ripper_index, mut position} = candidate;
+
+            // where position's offsets are counted from
+            let mut base: u64 = chunk_start;
+            let mut data_length: usize = window.len();
+            let mut data_is_last: bool = window_is_last;
+            let mut extended_size: usize = window_size;
+
+            while position.end == data_length && !data_is_last && extended_size < options.max_content_size {
+                // content runs into the end of what's been read, give it more data
+                extended_size = std::cmp::min(extended_size.saturating_mul(2), options.max_content_size);
+                let content_start: u64 = base + position.start as u64;
+                read_window(source, content_start, extended_size, &mut extended_window)?;
+                data_is_last = content_start + extended_window.len() as u64 >= source_length;
+
+                match engine.rippers[ripper_index].rip(&extended_window, 0) {
+                    Some(extended_position) => {
+                        position = extended_position;
+                        base = content_start;
+                        data_length = extended_window.len();
+                    }
+                    None => {
+                        break;
+                    }
+                }
+            }
+
+            position.start += base as usize;
+            position.end += base as usize;
+            if !engine.rippers[ripper_index].can_nest() {
+                skip_until[ripper_index] = std::cmp::max(skip_until[ripper_index], position.end);
+            }
+            positions.push(position);
+        }
+
+        chunk_start += chunk_size as u64;
+    }
+
+    return Ok(resolve_overlaps(positions, options.overlap_policy));
+}
+
+// Copies content at position from source straight into destination,
+// returns the amount of bytes copied
+pub fn copy_position<R: Read + Seek, W: Write>(source: &mut R, position: &Position, destination: &mut W) -> io::Result<u64> {
+    source.seek(SeekFrom::Start(position.start as u64))?;
+    return io::copy(&mut source.take((position.end - position.start) as u64), destination);
+}
+
+// Calculates SHA-256 of content at position without reading it into memory
+pub fn hash_position<R: Read + Seek>(source: &mut R, position: &Position) -> io::Result<[u8; 32]> {
+    let mut hasher: Sha256 = Sha256::new();
+    copy_position(source, position, &mut hasher)?;
+    return Ok(hasher.finalize());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::util::rip_type::RipType;
+
+    // 1x1 PNG with nothing but IHDR and IEND
+    const PNG: [u8; 45] = [
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
+        0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53,
+        0xDE, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+
+    #[test]
+    fn signatures_crossing_chunk_boundaries_are_found_once() {
+        let options: ScanOptions = ScanOptions{
+            rip_type: RipType::IMG,
+            chunk_size: 16,
+            ..ScanOptions::default()
+        };
+
+        // signatures start right before boundaries, on them and inside of chunks
+        let starts: [usize; 4] = [12, 64, 131, 250];
+        let mut data: Vec<u8> = vec![0x55; 320];
+        for start in starts {
+            data[start..start + PNG.len()].copy_from_slice(&PNG);
+        }
+
+        let positions: Vec<Position> = scan_stream(&mut Cursor::new(&data), &options).expect("data was not read");
+        let found: Vec<(usize, usize)> = positions.iter().map(|position| (position.start, position.end)).collect();
+        let expected: Vec<(usize, usize)> = starts.iter().map(|start| (*start, start + PNG.len())).collect();
+        assert_eq!(found, expected);
+    }
+}
@@ -16,9 +16,77 @@ You should have received a copy of the GNU General Public License
 along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ContentType {
     PNG,
     JPEG,
+    GIF,
+    BMP,
+    DIB,
+    WEBP,
+    ANI,
+    HEIC,
+    HEIF,
+    AVIF,
     MP3,
+    MP2,
+    MP1,
+    WAV,
+    OGG,
+    OPUS,
+    FLAC,
+    AAC,
+    M4A,
+    AVI,
+    MP4,
+    MOV,
+    THREEGP,
+    THREEG2,
+    MKV,
+    WEBM,
+    ZIP,
+    GZIP,
+    ZLIB,
+}
+
+impl ContentType {
+    // Returns file extension (without a dot) for this kind of content
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ContentType::PNG => return "png",
+            ContentType::JPEG => return "jpeg",
+            ContentType::GIF => return "gif",
+            ContentType::BMP => return "bmp",
+            ContentType::DIB => return "dib",
+            ContentType::WEBP => return "webp",
+            ContentType::ANI => return "ani",
+            ContentType::HEIC => return "heic",
+            ContentType::HEIF => return "heif",
+            ContentType::AVIF => return "avif",
+            ContentType::MP3 => return "mp3",
+            ContentType::MP2 => return "mp2",
+            ContentType::MP1 => return "mp1",
+            ContentType::WAV => return "wav",
+            ContentType::OGG => return "ogg",
+            ContentType::OPUS => return "opus",
+            ContentType::FLAC => return "flac",
+            ContentType::AAC => return "aac",
+            ContentType::M4A => return "m4a",
+            ContentType::AVI => return "avi",
+            ContentType::MP4 => return "mp4",
+            ContentType::MOV => return "mov",
+            ContentType::THREEGP => return "3gp",
+            ContentType::THREEG2 => return "3g2",
+            ContentType::MKV => return "mkv",
+            ContentType::WEBM => return "webm",
+            ContentType::ZIP => return "zip",
+            ContentType::GZIP => return "gz",
+            ContentType::ZLIB => return "zlib",
+        }
+    }
+
+    // Whether this content is compressed data that can be looked into
+    pub fn is_compressed(&self) -> bool {
+        return *self == ContentType::GZIP || *self == ContentType::ZLIB;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// CRC-32 as used by PNG, ZIP and gzip (reflected, polynomial 0xEDB88320)
+const CRC32_TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table: [u32; 256] = [0; 256];
+    let mut n: usize = 0;
+    while n < 256 {
+        let mut c: u32 = n as u32;
+        let mut k: usize = 0;
+        while k < 8 {
+            if c & 1 != 0 {
+                c = 0xEDB88320 ^ (c >> 1);
+            } else {
+                c = c >> 1;
+            }
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    return table;
+}
+
+// Continues calculating crc over more data. Start with crc = 0
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut c: u32 = crc ^ 0xFFFFFFFF;
+    for byte in data {
+        c = CRC32_TABLE[((c ^ *byte as u32) & 0xFF) as usize] ^ (c >> 8);
+    }
+    return c ^ 0xFFFFFFFF;
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    return crc32_update(0, data);
+}
+
+
+// CRC-32 as used by Ogg (not reflected, polynomial 0x04C11DB7, no inversion)
+const OGG_CRC32_TABLE: [u32; 256] = make_ogg_table();
+
+const fn make_ogg_table() -> [u32; 256] {
+    let mut table: [u32; 256] = [0; 256];
+    let mut n: usize = 0;
+    while n < 256 {
+        let mut c: u32 = (n as u32) << 24;
+        let mut k: usize = 0;
+        while k < 8 {
+            if c & 0x80000000 != 0 {
+                c = (c << 1) ^ 0x04C11DB7;
+            } else {
+                c = c << 1;
+            }
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    return table;
+}
+
+// Continues calculating Ogg crc over more data. Start with crc = 0
+pub fn ogg_crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut c: u32 = crc;
+    for byte in data {
+        c = OGG_CRC32_TABLE[(((c >> 24) ^ *byte as u32) & 0xFF) as usize] ^ (c << 8);
+    }
+    return c;
+}
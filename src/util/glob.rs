@@ -0,0 +1,115 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// Matches text against a shell-like pattern:
+// "*" - any amount of characters except for "/"
+// "**" - any amount of any characters
+// "?" - a single character except for "/"
+// "[abc]", "[a-z]", "[!abc]" - a single character from (or not from) the set
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    return match_from(&pattern, &text);
+}
+
+// Tries to match a character class that starts right after "[",
+// returns whether character matched and the index right after "]"
+fn match_class(pattern: &[char], character: char) -> Option<(bool, usize)> {
+    let mut i: usize = 0;
+    let negated: bool = i < pattern.len() && (pattern[i] == '!' || pattern[i] == '^');
+    if negated {
+        i += 1;
+    }
+
+    let mut matched: bool = false;
+    let mut is_first: bool = true;
+    while i < pattern.len() {
+        if pattern[i] == ']' && !is_first {
+            return Some((matched != negated, i + 1));
+        }
+        is_first = false;
+
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            if pattern[i] <= character && character <= pattern[i + 2] {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if pattern[i] == character {
+                matched = true;
+            }
+            i += 1;
+        }
+    }
+
+    // no closing bracket
+    return None;
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    if pattern.is_empty() {
+        return text.is_empty();
+    }
+
+    match pattern[0] {
+        '*' => {
+            let crosses_separators: bool = pattern.len() > 1 && pattern[1] == '*';
+            let rest: &[char] = if crosses_separators {&pattern[2..]} else {&pattern[1..]};
+            if crosses_separators {
+                if rest.is_empty() {
+                    return true;
+                }
+                // "**/" also matches no directories at all
+                if rest[0] == '/' && match_from(&rest[1..], text) {
+                    return true;
+                }
+            }
+
+            for i in 0..=text.len() {
+                if match_from(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' && !crosses_separators {
+                    break;
+                }
+            }
+
+            return false;
+        }
+
+        '?' => {
+            return !text.is_empty() && text[0] != '/' && match_from(&pattern[1..], &text[1..]);
+        }
+
+        '[' => {
+            if text.is_empty() || text[0] == '/' {
+                return false;
+            }
+            match match_class(&pattern[1..], text[0]) {
+                Some((true, class_length)) => return match_from(&pattern[1 + class_length..], &text[1..]),
+                Some((false, _)) => return false,
+                // treat a lone "[" literally
+                None => return text[0] == '[' && match_from(&pattern[1..], &text[1..]),
+            }
+        }
+
+        character => {
+            return !text.is_empty() && text[0] == character && match_from(&pattern[1..], &text[1..]);
+        }
+    }
+}
@@ -0,0 +1,500 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// DEFLATE (RFC 1951) decoder
+
+const MAX_CODE_LENGTH: usize = 15;
+const END_OF_BLOCK: u16 = 256;
+// back-references reach no further than that
+const WINDOW_SIZE: usize = 32 * 1024;
+// how much decompressed data piles up before it's let go of
+const TRIM_LENGTH: usize = 8 * WINDOW_SIZE;
+
+// base lengths and extra bits for length symbols 257..285
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+// base distances and extra bits for distance symbols 0..29
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769,
+    1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+// order in which code length code lengths are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// Why a stream could not be inflated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InflateError {
+    // data ended before the stream did
+    TRUNCATED,
+    // the stream is malformed
+    INVALID,
+    // the stream decompresses to more than allowed
+    OVERSIZED,
+}
+
+// Reads bits least significant first
+struct BitReader<'a> {
+    data: &'a [u8],
+    // index of the next byte to take bits from
+    index: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn bits(&mut self, count: u32) -> Result<u32, InflateError> {
+        while self.bit_count < count {
+            if self.index >= self.data.len() {
+                return Err(InflateError::TRUNCATED);
+            }
+            self.bit_buffer |= (self.data[self.index] as u32) << self.bit_count;
+            self.index += 1;
+            self.bit_count += 8;
+        }
+
+        let value: u32 = self.bit_buffer & ((1u32 << count) - 1);
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        return Ok(value);
+    }
+
+    // Drops what's left of the current byte
+    fn align_to_byte(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+}
+
+// Decompressed bytes, of which only the window back-references can reach has to be kept
+struct Output<'a> {
+    bytes: Vec<u8>,
+    // how many bytes were produced, including those that are not kept anymore
+    length: usize,
+    max_length: usize,
+    keep_all: bool,
+    // gets bytes before they are let go of
+    consume: &'a mut dyn FnMut(&[u8]),
+}
+
+impl<'a> Output<'a> {
+    fn new(max_length: usize, keep_all: bool, consume: &'a mut dyn FnMut(&[u8])) -> Output<'a> {
+        return Output{
+            bytes: Vec::new(),
+            length: 0,
+            max_length: max_length,
+            keep_all: keep_all,
+            consume: consume,
+        };
+    }
+
+    fn push(&mut self, byte: u8) -> Result<(), InflateError> {
+        if self.length >= self.max_length {
+            return Err(InflateError::OVERSIZED);
+        }
+        self.bytes.push(byte);
+        self.length += 1;
+        self.trim();
+        return Ok(());
+    }
+
+    fn extend(&mut self, data: &[u8]) -> Result<(), InflateError> {
+        if self.length + data.len() > self.max_length {
+            return Err(InflateError::OVERSIZED);
+        }
+        self.bytes.extend_from_slice(data);
+        self.length += data.len();
+        self.trim();
+        return Ok(());
+    }
+
+    // Repeats length bytes that start distance bytes back, the copy may overlap with itself
+    fn copy(&mut self, distance: usize, length: usize) -> Result<(), InflateError> {
+        if distance > self.bytes.len() {
+            return Err(InflateError::INVALID);
+        }
+        if self.length + length > self.max_length {
+            return Err(InflateError::OVERSIZED);
+        }
+
+        let copy_start: usize = self.bytes.len() - distance;
+        for i in 0..length {
+            let byte: u8 = self.bytes[copy_start + i];
+            self.bytes.push(byte);
+        }
+        self.length += length;
+        self.trim();
+        return Ok(());
+    }
+
+    // Lets go of everything but the window once there's enough to let go of
+    fn trim(&mut self) {
+        if self.keep_all || self.bytes.len() < TRIM_LENGTH {
+            return;
+        }
+
+        let drop_length: usize = self.bytes.len() - WINDOW_SIZE;
+        (self.consume)(&self.bytes[..drop_length]);
+        self.bytes.drain(..drop_length);
+    }
+
+    // Hands what's left to consume
+    fn finish(&mut self) {
+        if !self.keep_all {
+            (self.consume)(&self.bytes);
+            self.bytes.clear();
+        }
+    }
+}
+
+// Canonical Huffman code as symbol counts per code length and symbols sorted by their codes
+struct Huffman {
+    counts: [u16; MAX_CODE_LENGTH + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    // Builds a code out of code lengths per symbol, incomplete codes are allowed
+    // as long as they are not over-subscribed
+    fn new(lengths: &[u8]) -> Result<Huffman, InflateError> {
+        let mut counts: [u16; MAX_CODE_LENGTH + 1] = [0; MAX_CODE_LENGTH + 1];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+
+        let mut left: i32 = 1;
+        for length in 1..=MAX_CODE_LENGTH {
+            left = (left << 1) - counts[length] as i32;
+            if left < 0 {
+                return Err(InflateError::INVALID);
+            }
+        }
+
+        let mut offsets: [u16; MAX_CODE_LENGTH + 2] = [0; MAX_CODE_LENGTH + 2];
+        for length in 1..=MAX_CODE_LENGTH {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols: Vec<u16> = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        return Ok(Huffman{
+            counts: counts,
+            symbols: symbols,
+        });
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, InflateError> {
+        // codes are stored most significant bit first, so go bit by bit
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for length in 1..=MAX_CODE_LENGTH {
+            code |= reader.bits(1)? as i32;
+            let count: i32 = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+
+        return Err(InflateError::INVALID);
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths: [u8; 288] = [0; 288];
+    for (symbol, length) in lengths.iter_mut().enumerate() {
+        *length = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+
+    let literals: Huffman = Huffman::new(&lengths).expect("fixed literal code is over-subscribed");
+    let distances: Huffman = Huffman::new(&[5; 30]).expect("fixed distance code is over-subscribed");
+    return (literals, distances);
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), InflateError> {
+    let literal_count: usize = reader.bits(5)? as usize + 257;
+    let distance_count: usize = reader.bits(5)? as usize + 1;
+    let code_length_count: usize = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(InflateError::INVALID);
+    }
+
+    let mut code_length_lengths: [u8; 19] = [0; 19];
+    for symbol in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_length_lengths[*symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code: Huffman = Huffman::new(&code_length_lengths)?;
+
+    let mut lengths: Vec<u8> = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let symbol: u16 = code_length_code.decode(reader)?;
+        let (value, repeat): (u8, u32) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                match lengths.last() {
+                    Some(previous) => (*previous, 3 + reader.bits(2)?),
+                    None => return Err(InflateError::INVALID),
+                }
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+
+        if lengths.len() + repeat as usize > literal_count + distance_count {
+            return Err(InflateError::INVALID);
+        }
+        for _ in 0..repeat {
+            lengths.push(value);
+        }
+    }
+
+    if lengths[END_OF_BLOCK as usize] == 0 {
+        // there would be no way to end the block
+        return Err(InflateError::INVALID);
+    }
+
+    let literals: Huffman = Huffman::new(&lengths[..literal_count])?;
+    let distances: Huffman = Huffman::new(&lengths[literal_count..])?;
+    return Ok((literals, distances));
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Output,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), InflateError> {
+    loop {
+        let symbol: u16 = literals.decode(reader)?;
+        if symbol < END_OF_BLOCK {
+            output.push(symbol as u8)?;
+            continue;
+        }
+        if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+
+        let length_index: usize = (symbol - 257) as usize;
+        if length_index >= LENGTH_BASES.len() {
+            return Err(InflateError::INVALID);
+        }
+        let length: usize = LENGTH_BASES[length_index] as usize + reader.bits(LENGTH_EXTRA_BITS[length_index] as u32)? as usize;
+
+        let distance_index: usize = distances.decode(reader)? as usize;
+        if distance_index >= DISTANCE_BASES.len() {
+            return Err(InflateError::INVALID);
+        }
+        let distance: usize = DISTANCE_BASES[distance_index] as usize + reader.bits(DISTANCE_EXTRA_BITS[distance_index] as u32)? as usize;
+        output.copy(distance, length)?;
+    }
+}
+
+fn new_reader(data: &[u8]) -> BitReader<'_> {
+    return BitReader{
+        data: data,
+        index: 0,
+        bit_buffer: 0,
+        bit_count: 0,
+    };
+}
+
+// Goes through blocks of the stream reader is at, returns the length of the stream
+fn inflate_blocks(reader: &mut BitReader, output: &mut Output) -> Result<usize, InflateError> {
+    let data: &[u8] = reader.data;
+    loop {
+        let is_last_block: bool = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                // stored block, byte aligned length and its complement
+                reader.align_to_byte();
+                if reader.index + 4 > data.len() {
+                    return Err(InflateError::TRUNCATED);
+                }
+                let length: usize = u16::from_le_bytes([data[reader.index], data[reader.index + 1]]) as usize;
+                let complement: usize = u16::from_le_bytes([data[reader.index + 2], data[reader.index + 3]]) as usize;
+                if length != !complement & 0xFFFF {
+                    return Err(InflateError::INVALID);
+                }
+                reader.index += 4;
+
+                if reader.index + length > data.len() {
+                    return Err(InflateError::TRUNCATED);
+                }
+                output.extend(&data[reader.index..reader.index + length])?;
+                reader.index += length;
+            }
+            1 => {
+                let (literals, distances): (Huffman, Huffman) = fixed_codes();
+                inflate_block(reader, output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances): (Huffman, Huffman) = dynamic_codes(reader)?;
+                inflate_block(reader, output, &literals, &distances)?;
+            }
+            _ => {
+                return Err(InflateError::INVALID);
+            }
+        }
+
+        if is_last_block {
+            break;
+        }
+    }
+
+    // bits left in the buffer belong to the last byte of the stream
+    return Ok(reader.index);
+}
+
+// Decompresses a raw DEFLATE stream at the beginning of data, producing no more than
+// max_output_length bytes. Returns decompressed bytes and the length of the compressed stream
+pub fn inflate(data: &[u8], max_output_length: usize) -> Result<(Vec<u8>, usize), InflateError> {
+    let mut consume = |_: &[u8]| {};
+    let mut output: Output = Output::new(max_output_length, true, &mut consume);
+    let compressed_length: usize = inflate_blocks(&mut new_reader(data), &mut output)?;
+    return Ok((output.bytes, compressed_length));
+}
+
+// How far inflate_into got
+#[derive(Debug, Clone, Copy)]
+pub struct InflatedLengths {
+    pub decompressed_length: usize,
+    // compressed bytes taken, which is the whole stream if it's complete
+    pub compressed_length: usize,
+    // false if decompressing stopped at the output limit before the stream ended
+    pub is_complete: bool,
+}
+
+// Decompresses a raw DEFLATE stream at the beginning of data like inflate does, but instead
+// of keeping decompressed bytes hands them to consume piece by piece, so streams can be checked
+// without holding them in memory. Reaching max_output_length is not an error here, decompressing
+// just stops there, so only a valid beginning of the stream is known then
+pub fn inflate_into(data: &[u8], max_output_length: usize, consume: &mut dyn FnMut(&[u8])) -> Result<InflatedLengths, InflateError> {
+    let mut reader: BitReader = new_reader(data);
+    let mut output: Output = Output::new(max_output_length, false, consume);
+    let is_complete: bool;
+    match inflate_blocks(&mut reader, &mut output) {
+        Ok(_) => {
+            is_complete = true;
+        }
+        Err(InflateError::OVERSIZED) => {
+            is_complete = false;
+        }
+        Err(error) => {
+            return Err(error);
+        }
+    }
+    output.finish();
+
+    return Ok(InflatedLengths{
+        decompressed_length: output.length,
+        compressed_length: reader.index,
+        is_complete: is_complete,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "hello, stored" in a single stored block
+    const STORED: [u8; 18] = [
+        0x01, 0x0D, 0x00, 0xF2, 0xFF, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x2C, 0x20, 0x73, 0x74, 0x6F, 0x72,
+        0x65, 0x64,
+    ];
+
+    // "hello hello hello fixed" in a block with fixed Huffman codes
+    const FIXED: [u8; 15] = [0xCB, 0x48, 0xCD, 0xC9, 0xC9, 0x57, 0xC8, 0x40, 0x22, 0xD3, 0x32, 0x2B, 0x52, 0x53, 0x00];
+
+    // DYNAMIC_TEXT in a block with dynamic Huffman codes
+    const DYNAMIC: [u8; 78] = [
+        0xCD, 0xCB, 0xC9, 0x11, 0x80, 0x20, 0x10, 0x44, 0xD1, 0x54, 0x3A, 0x0F, 0xA3, 0x01, 0x65, 0x53,
+        0x60, 0xD8, 0x11, 0xA3, 0x77, 0xCA, 0x28, 0x3C, 0x76, 0xFD, 0xD7, 0xCD, 0x2A, 0xE4, 0xEE, 0xF6,
+        0x0B, 0xB2, 0xD0, 0x8C, 0xD0, 0x74, 0xE3, 0xEC, 0x21, 0x55, 0xD0, 0x50, 0x05, 0x8D, 0xB3, 0x17,
+        0xCF, 0xC2, 0x41, 0x66, 0xFB, 0xD6, 0x0F, 0x70, 0x12, 0xEC, 0xC2, 0x82, 0x64, 0x34, 0x5D, 0xB3,
+        0xD0, 0x6E, 0x28, 0x4E, 0x8F, 0x8A, 0xF0, 0x2E, 0x77, 0x2A, 0xFC, 0x35, 0xF5, 0x05,
+    ];
+
+    fn dynamic_text() -> Vec<u8> {
+        let mut text: Vec<u8> = b"the quick brown fox jumps over the lazy dog; ".repeat(4);
+        text.extend_from_slice(b"pack my box with five dozen liquor jugs");
+        return text;
+    }
+
+    #[test]
+    fn stored_block() {
+        let mut data: Vec<u8> = STORED.to_vec();
+        // whatever follows the stream is not taken
+        data.extend_from_slice(b"trailer");
+        assert_eq!(inflate(&data, 1000), Ok((b"hello, stored".to_vec(), STORED.len())));
+    }
+
+    #[test]
+    fn fixed_block() {
+        assert_eq!(inflate(&FIXED, 1000), Ok((b"hello hello hello fixed".to_vec(), FIXED.len())));
+    }
+
+    #[test]
+    fn dynamic_block() {
+        assert_eq!(inflate(&DYNAMIC, 1000), Ok((dynamic_text(), DYNAMIC.len())));
+    }
+
+    #[test]
+    fn truncated_input() {
+        for compressed in [&STORED[..], &FIXED[..], &DYNAMIC[..]] {
+            assert_eq!(inflate(&compressed[..compressed.len() - 3], 1000), Err(InflateError::TRUNCATED));
+        }
+    }
+
+    #[test]
+    fn output_limit() {
+        assert_eq!(inflate(&DYNAMIC, 100), Err(InflateError::OVERSIZED));
+
+        let mut consumed: Vec<u8> = Vec::new();
+        let lengths: InflatedLengths = inflate_into(&DYNAMIC, 100, &mut |bytes: &[u8]| consumed.extend_from_slice(bytes))
+            .expect("a valid beginning was not accepted");
+        assert!(!lengths.is_complete);
+        assert!(lengths.decompressed_length <= 100);
+        assert_eq!(consumed, dynamic_text()[..lengths.decompressed_length].to_vec());
+
+        let lengths: InflatedLengths = inflate_into(&DYNAMIC, 1000, &mut |_: &[u8]| {}).expect("stream was not inflated");
+        assert!(lengths.is_complete);
+        assert_eq!(lengths.decompressed_length, dynamic_text().len());
+        assert_eq!(lengths.compressed_length, DYNAMIC.len());
+    }
+}
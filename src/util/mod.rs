@@ -1,2 +1,12 @@
 pub mod position;
-pub mod content_type;
\ No newline at end of file
+pub mod content_type;
+pub mod scanner;
+pub mod rip_type;
+pub mod ripper;
+pub mod overlap;
+pub mod validity;
+pub mod crc32;
+pub mod sha256;
+pub mod glob;
+pub mod walk;
+pub mod inflate;
\ No newline at end of file
@@ -0,0 +1,231 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::validity::Validity;
+
+// What to do with content that lies inside (or partially on top of) other content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapPolicy {
+    // keep only the biggest, top-level content
+    OUTERMOST,
+    // keep only the content that does not contain anything else
+    INNERMOST,
+    // keep everything, but remember what lies inside of what
+    ALL,
+}
+
+fn contains(outer: &Position, inner: &Position) -> bool {
+    return outer.start <= inner.start && inner.end <= outer.end;
+}
+
+// Finds the smallest containing position for each of sorted positions
+fn find_parents(positions: &[Position]) -> Vec<Option<usize>> {
+    let mut parents: Vec<Option<usize>> = vec![None; positions.len()];
+
+    // chain of positions that the current one might be inside of
+    let mut open: Vec<usize> = Vec::new();
+    for i in 0..positions.len() {
+        // forget about the ones that are already behind
+        open.retain(|&j| positions[j].end > positions[i].start);
+
+        for &j in open.iter().rev() {
+            if contains(&positions[j], &positions[i]) {
+                parents[i] = Some(j);
+                break;
+            }
+        }
+
+        open.push(i);
+    }
+
+    return parents;
+}
+
+// Drops sorted positions that are not valid where a valid one partially overlaps them,
+// so a broken or made up position can't push real content out
+fn prefer_valid(positions: Vec<Position>) -> Vec<Position> {
+    let mut is_dropped: Vec<bool> = vec![false; positions.len()];
+
+    let mut open: Vec<usize> = Vec::new();
+    for i in 0..positions.len() {
+        open.retain(|&j| positions[j].end > positions[i].start);
+
+        for &j in open.iter() {
+            if contains(&positions[j], &positions[i]) {
+                continue;
+            }
+
+            // j starts first and ends inside of i
+            let is_i_valid: bool = positions[i].validity == Validity::VALID;
+            let is_j_valid: bool = positions[j].validity == Validity::VALID;
+            if is_j_valid && !is_i_valid {
+                is_dropped[i] = true;
+            }
+            else if is_i_valid && !is_j_valid {
+                is_dropped[j] = true;
+            }
+        }
+
+        open.push(i);
+    }
+
+    return positions
+        .into_iter()
+        .zip(is_dropped)
+        .filter(|(_, is_dropped)| !is_dropped)
+        .map(|(position, _)| position)
+        .collect();
+}
+
+// Sorts positions by their start and gets rid of overlaps as the policy says.
+// Positions are dropped only when they lie inside of a kept one. Of two partially overlapping
+// positions a valid one wins over a broken one, otherwise the first one is kept.
+// Fills in parent for each nested position if the policy keeps them
+pub fn resolve_overlaps(mut positions: Vec<Position>, policy: OverlapPolicy) -> Vec<Position> {
+    // outer ones come first on equal starts
+    positions.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+    if policy != OverlapPolicy::ALL {
+        positions = prefer_valid(positions);
+    }
+
+    let parents: Vec<Option<usize>> = find_parents(&positions);
+
+    let mut resolved: Vec<Position> = Vec::with_capacity(positions.len());
+    match policy {
+        OverlapPolicy::ALL => {
+            for (mut position, parent) in positions.into_iter().zip(parents) {
+                position.parent = parent;
+                resolved.push(position);
+            }
+        }
+
+        OverlapPolicy::OUTERMOST => {
+            // kept positions don't overlap, so anything starting before the last one ends
+            // is either inside of it or partially overlaps it
+            let mut covered_until: usize = 0;
+            for mut position in positions {
+                if position.start < covered_until {
+                    continue;
+                }
+                covered_until = position.end;
+                position.parent = None;
+                resolved.push(position);
+            }
+        }
+
+        OverlapPolicy::INNERMOST => {
+            let mut has_children: Vec<bool> = vec![false; positions.len()];
+            for parent in parents.iter().flatten() {
+                has_children[*parent] = true;
+            }
+
+            let mut covered_until: usize = 0;
+            for (i, mut position) in positions.into_iter().enumerate() {
+                if has_children[i] || position.start < covered_until {
+                    continue;
+                }
+                covered_until = position.end;
+                position.parent = None;
+                resolved.push(position);
+            }
+        }
+    }
+
+    return resolved;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::content_type::ContentType;
+
+    fn position(start: usize, end: usize, validity: Validity) -> Position {
+        return Position{
+            start: start,
+            end: end,
+            content_type: ContentType::PNG,
+            validity: validity,
+            parent: None,
+            metadata: Vec::new(),
+        };
+    }
+
+    fn ranges(positions: &[Position]) -> Vec<(usize, usize)> {
+        return positions.iter().map(|position| (position.start, position.end)).collect();
+    }
+
+    // an outer position holding two inner ones, and one more after it
+    fn nested_positions() -> Vec<Position> {
+        return vec![
+            position(120, 150, Validity::VALID),
+            position(20, 40, Validity::VALID),
+            position(0, 100, Validity::VALID),
+            position(50, 60, Validity::VALID),
+        ];
+    }
+
+    #[test]
+    fn outermost_keeps_top_level_positions() {
+        let resolved: Vec<Position> = resolve_overlaps(nested_positions(), OverlapPolicy::OUTERMOST);
+        assert_eq!(ranges(&resolved), vec![(0, 100), (120, 150)]);
+    }
+
+    #[test]
+    fn innermost_keeps_positions_without_children() {
+        let resolved: Vec<Position> = resolve_overlaps(nested_positions(), OverlapPolicy::INNERMOST);
+        assert_eq!(ranges(&resolved), vec![(20, 40), (50, 60), (120, 150)]);
+    }
+
+    #[test]
+    fn all_keeps_everything_with_parents() {
+        let resolved: Vec<Position> = resolve_overlaps(nested_positions(), OverlapPolicy::ALL);
+        assert_eq!(ranges(&resolved), vec![(0, 100), (20, 40), (50, 60), (120, 150)]);
+        let parents: Vec<Option<usize>> = resolved.iter().map(|position| position.parent).collect();
+        assert_eq!(parents, vec![None, Some(0), Some(0), None]);
+    }
+
+    #[test]
+    fn valid_position_wins_partial_overlap() {
+        let positions: Vec<Position> = vec![
+            position(0, 100, Validity::TRUNCATED),
+            // inside of the truncated one, but not of the valid one that replaces it
+            position(10, 20, Validity::VALID),
+            position(90, 200, Validity::VALID),
+        ];
+
+        for policy in [OverlapPolicy::OUTERMOST, OverlapPolicy::INNERMOST] {
+            let resolved: Vec<Position> = resolve_overlaps(positions.clone(), policy);
+            assert_eq!(ranges(&resolved), vec![(10, 20), (90, 200)]);
+        }
+    }
+
+    #[test]
+    fn first_position_wins_partial_overlap_of_equals() {
+        let positions: Vec<Position> = vec![
+            position(50, 150, Validity::CORRUPT),
+            position(0, 100, Validity::TRUNCATED),
+        ];
+
+        for policy in [OverlapPolicy::OUTERMOST, OverlapPolicy::INNERMOST] {
+            let resolved: Vec<Position> = resolve_overlaps(positions.clone(), policy);
+            assert_eq!(ranges(&resolved), vec![(0, 100)]);
+        }
+    }
+}
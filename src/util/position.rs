@@ -17,10 +17,16 @@ along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
 use crate::util::content_type::ContentType;
+use crate::util::validity::Validity;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Position {
     pub start: usize,
     pub end: usize,
     pub content_type: ContentType,
+    pub validity: Validity,
+    // index of the position this one lies inside of, if any
+    pub parent: Option<usize>,
+    // format specific details worth reporting, like dimensions or sampling rate
+    pub metadata: Vec<(&'static str, String)>,
 }
\ No newline at end of file
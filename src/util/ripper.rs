@@ -0,0 +1,55 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::util::position::Position;
+use crate::util::content_type::ContentType;
+use crate::util::rip_type::RipType;
+
+// Everything needed to find and carve out one particular format
+pub trait Ripper {
+    // human-readable format name
+    fn name(&self) -> &'static str;
+
+    fn content_type(&self) -> ContentType;
+
+    // what kind of content this is, used to pick rippers by RIPTYPE
+    fn rip_type(&self) -> RipType;
+
+    // signatures that the content always starts with
+    fn magic(&self) -> &'static [&'static [u8]];
+
+    // how far from the start of the content its signatures are
+    fn magic_offset(&self) -> usize {
+        return 0;
+    }
+
+    // Whether content of this format may lie inside of other content of the same format.
+    // If not, signatures inside of already ripped content are not looked at again
+    fn can_nest(&self) -> bool {
+        return true;
+    }
+
+    fn extension(&self) -> &'static str {
+        return self.content_type().extension();
+    }
+
+    // Tries to parse content that starts exactly at start_index.
+    // Content that seems to continue past the end of data should be
+    // returned ending at data.len(), so a bigger piece of data can be tried
+    fn rip(&self, data: &[u8], start_index: usize) -> Option<Position>;
+}
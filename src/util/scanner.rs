@@ -0,0 +1,206 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+const ALPHABET_SIZE: usize = 256;
+const ROOT_STATE: u32 = 0;
+
+// A signature occurrence found by the scanner
+#[derive(Debug)]
+pub struct Match {
+    // index of the signature as it was given to Scanner::new
+    pub signature: usize,
+    // offset of the first byte of the signature in the scanned data
+    pub start: usize,
+}
+
+// Aho-Corasick automaton that finds every occurrence of
+// every registered signature in a single pass over the data
+pub struct Scanner {
+    // transitions[state * ALPHABET_SIZE + byte] -> next state
+    transitions: Vec<u32>,
+    // signatures that end in a particular state
+    outputs: Vec<Vec<usize>>,
+    signature_lengths: Vec<usize>,
+}
+
+impl Scanner {
+    pub fn new(signatures: &[&[u8]]) -> Scanner {
+        let mut transitions: Vec<u32> = vec![u32::MAX; ALPHABET_SIZE];
+        let mut outputs: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut signature_lengths: Vec<usize> = Vec::with_capacity(signatures.len());
+
+        // build a trie out of signatures
+        for (signature_index, signature) in signatures.iter().enumerate() {
+            signature_lengths.push(signature.len());
+            if signature.len() == 0 {
+                continue;
+            }
+
+            let mut state: u32 = ROOT_STATE;
+            for byte in signature.iter() {
+                let transition_index: usize = state as usize * ALPHABET_SIZE + *byte as usize;
+                if transitions[transition_index] == u32::MAX {
+                    // add a new state
+                    let new_state: u32 = outputs.len() as u32;
+                    transitions[transition_index] = new_state;
+                    transitions.extend_from_slice(&[u32::MAX; ALPHABET_SIZE]);
+                    outputs.push(Vec::new());
+                }
+                state = transitions[transition_index];
+            }
+            outputs[state as usize].push(signature_index);
+        }
+
+        // compute failure links breadth-first and turn the trie into a complete DFA
+        let mut failure: Vec<u32> = vec![ROOT_STATE; outputs.len()];
+        let mut queue: VecDeque<u32> = VecDeque::new();
+
+        for byte in 0..ALPHABET_SIZE {
+            let next: u32 = transitions[byte];
+            if next == u32::MAX {
+                transitions[byte] = ROOT_STATE;
+            } else {
+                failure[next as usize] = ROOT_STATE;
+                queue.push_back(next);
+            }
+        }
+
+        while let Some(state) = queue.pop_front() {
+            let state_fail: u32 = failure[state as usize];
+
+            // inherit matches of the longest proper suffix
+            let inherited: Vec<usize> = outputs[state_fail as usize].clone();
+            outputs[state as usize].extend(inherited);
+
+            for byte in 0..ALPHABET_SIZE {
+                let transition_index: usize = state as usize * ALPHABET_SIZE + byte;
+                let next: u32 = transitions[transition_index];
+                let fallback: u32 = transitions[state_fail as usize * ALPHABET_SIZE + byte];
+                if next == u32::MAX {
+                    transitions[transition_index] = fallback;
+                } else {
+                    failure[next as usize] = fallback;
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        return Scanner{
+            transitions: transitions,
+            outputs: outputs,
+            signature_lengths: signature_lengths,
+        };
+    }
+
+    // Returns the length of the longest registered signature
+    pub fn max_signature_length(&self) -> usize {
+        return self.signature_lengths.iter().copied().max().unwrap_or(0);
+    }
+
+    // Goes through data once, yielding signature occurrences sorted by their start offsets
+    // as soon as no occurrence that starts earlier can turn up
+    pub fn scan<'a>(&'a self, data: &'a [u8]) -> Matches<'a> {
+        return Matches{
+            scanner: self,
+            data: data,
+            index: 0,
+            state: ROOT_STATE,
+            pending: BinaryHeap::new(),
+            found_count: 0,
+            max_signature_length: self.max_signature_length(),
+        };
+    }
+}
+
+// Signature occurrences in data, in order of their starts. Matches are found in order
+// of their ends, so ones that a longer signature could still precede are held back
+pub struct Matches<'a> {
+    scanner: &'a Scanner,
+    data: &'a [u8],
+    // how many bytes were fed to the automaton
+    index: usize,
+    state: u32,
+    // start, order of finding and signature of matches that can't be given out yet
+    pending: BinaryHeap<Reverse<(usize, usize, usize)>>,
+    found_count: usize,
+    max_signature_length: usize,
+}
+
+impl<'a> Iterator for Matches<'a> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            if let Some(Reverse((start, _, signature))) = self.pending.peek().copied() {
+                // anything found later ends at index or further, so it can't start before that
+                if self.index == self.data.len() || start + self.max_signature_length <= self.index {
+                    self.pending.pop();
+                    return Some(Match{
+                        signature: signature,
+                        start: start,
+                    });
+                }
+            }
+
+            if self.index == self.data.len() {
+                return None;
+            }
+
+            let byte: u8 = self.data[self.index];
+            self.state = self.scanner.transitions[self.state as usize * ALPHABET_SIZE + byte as usize];
+            self.index += 1;
+            for signature_index in self.scanner.outputs[self.state as usize].iter() {
+                let start: usize = self.index - self.scanner.signature_lengths[*signature_index];
+                self.pending.push(Reverse((start, self.found_count, *signature_index)));
+                self.found_count += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(scanner: &Scanner, data: &[u8]) -> Vec<(usize, usize)> {
+        return scanner.scan(data).map(|found_match| (found_match.start, found_match.signature)).collect();
+    }
+
+    #[test]
+    fn matches_come_in_order_of_their_starts() {
+        // "b" is found before "abc" ends, but starts after it
+        let scanner: Scanner = Scanner::new(&[b"abc", b"b", b"bcd"]);
+        assert_eq!(found(&scanner, b"xabcdab"), vec![(1, 0), (2, 1), (2, 2), (6, 1)]);
+    }
+
+    #[test]
+    fn overlapping_occurrences_are_all_found() {
+        let scanner: Scanner = Scanner::new(&[b"\xFF\xFF", b"\xFF\xFB"]);
+        assert_eq!(found(&scanner, b"\xFF\xFF\xFF\xFB"), vec![(0, 0), (1, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn signature_at_the_end_of_data() {
+        let scanner: Scanner = Scanner::new(&[b"PNG", b"G"]);
+        assert_eq!(found(&scanner, b"..PN"), vec![]);
+        assert_eq!(found(&scanner, b"..PNG"), vec![(2, 0), (4, 1)]);
+    }
+}
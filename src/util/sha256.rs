@@ -0,0 +1,182 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::io;
+
+const BLOCK_SIZE: usize = 64;
+
+const ROUND_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INITIAL_STATE: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+// SHA-256 that can be fed data piece by piece. Implements Write,
+// so anything can be copied straight into it
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; BLOCK_SIZE],
+    block_length: usize,
+    total_length: u64,
+}
+
+impl Default for Sha256 {
+    fn default() -> Sha256 {
+        return Sha256::new();
+    }
+}
+
+impl Sha256 {
+    pub fn new() -> Sha256 {
+        return Sha256{
+            state: INITIAL_STATE,
+            block: [0; BLOCK_SIZE],
+            block_length: 0,
+            total_length: 0,
+        };
+    }
+
+    fn compress(&mut self) {
+        let mut w: [u32; 64] = [0; 64];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                self.block[i * 4], self.block[i * 4 + 1], self.block[i * 4 + 2], self.block[i * 4 + 3]
+            ]);
+        }
+        for i in 16..64 {
+            let s0: u32 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1: u32 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1: u32 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice: u32 = (e & f) ^ (!e & g);
+            let temp1: u32 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(ROUND_CONSTANTS[i]).wrapping_add(w[i]);
+            let s0: u32 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority: u32 = (a & b) ^ (a & c) ^ (b & c);
+            let temp2: u32 = s0.wrapping_add(majority);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(temp1);
+            d = c;
+            c = b;
+            b = a;
+            a = temp1.wrapping_add(temp2);
+        }
+
+        for (state_word, value) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state_word = state_word.wrapping_add(value);
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.total_length += data.len() as u64;
+
+        let mut remaining: &[u8] = data;
+        while !remaining.is_empty() {
+            let to_copy: usize = std::cmp::min(BLOCK_SIZE - self.block_length, remaining.len());
+            self.block[self.block_length..self.block_length + to_copy].copy_from_slice(&remaining[..to_copy]);
+            self.block_length += to_copy;
+            remaining = &remaining[to_copy..];
+
+            if self.block_length == BLOCK_SIZE {
+                self.compress();
+                self.block_length = 0;
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> [u8; 32] {
+        let bit_length: u64 = self.total_length.wrapping_mul(8);
+
+        // a single 1 bit, zeroes up until the last 8 bytes and the length
+        self.update(&[0x80]);
+        while self.block_length != BLOCK_SIZE - 8 {
+            self.update(&[0x00]);
+        }
+        self.block[BLOCK_SIZE - 8..].copy_from_slice(&bit_length.to_be_bytes());
+        self.compress();
+
+        let mut digest: [u8; 32] = [0; 32];
+        for (i, state_word) in self.state.iter().enumerate() {
+            digest[i * 4..i * 4 + 4].copy_from_slice(&state_word.to_be_bytes());
+        }
+        return digest;
+    }
+}
+
+impl io::Write for Sha256 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+
+// Returns lowercase hexadecimal representation of a digest
+pub fn to_hex(digest: &[u8]) -> String {
+    return digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha256_hex(data: &[u8]) -> String {
+        let mut hasher: Sha256 = Sha256::new();
+        hasher.update(data);
+        return to_hex(&hasher.finalize());
+    }
+
+    #[test]
+    fn nist_vectors() {
+        assert_eq!(sha256_hex(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn million_a_fed_in_pieces() {
+        let mut hasher: Sha256 = Sha256::new();
+        // pieces that don't line up with blocks
+        for _ in 0..10000 {
+            hasher.update(&[b'a'; 37]);
+        }
+        hasher.update(&[b'a'; 1000000 - 10000 * 37]);
+        assert_eq!(to_hex(&hasher.finalize()), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+    }
+}
@@ -0,0 +1,38 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// How much the ripped content can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validity {
+    // structure checks out from start to end
+    VALID,
+    // content ends prematurely, the rest is missing or got overwritten
+    TRUNCATED,
+    // structure is complete, but checksums or fields do not add up
+    CORRUPT,
+}
+
+impl Validity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Validity::VALID => return "valid",
+            Validity::TRUNCATED => return "truncated",
+            Validity::CORRUPT => return "corrupt",
+        }
+    }
+}
@@ -0,0 +1,164 @@
+/*
+rip - rip embedded content
+Copyright (C) 2022 Kasyanov Nikolay Alexeyevich (Unbewohnte)
+
+This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+use crate::util::glob::glob_match;
+
+// A file found while walking a directory
+#[derive(Debug)]
+pub struct WalkedFile {
+    pub path: PathBuf,
+    // path of the file relative to the walked directory
+    pub relative_path: PathBuf,
+}
+
+// Include and exclude glob patterns for input paths.
+// Patterns without "/" are matched against the file name only,
+// patterns with it - against the whole path relative to the walked directory
+#[derive(Debug, Default)]
+pub struct PathFilter {
+    pub includes: Vec<String>,
+    pub excludes: Vec<String>,
+}
+
+// Joins path components with "/" no matter the platform, so patterns are written the same way everywhere
+fn to_slash_path(path: &Path) -> String {
+    let components: Vec<String> = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    return components.join("/");
+}
+
+fn matches_any(patterns: &[String], relative_path: &Path) -> bool {
+    let slash_path: String = to_slash_path(relative_path);
+    let file_name: String = match relative_path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => slash_path.clone(),
+    };
+
+    for pattern in patterns {
+        let subject: &str = if pattern.contains('/') {&slash_path} else {&file_name};
+        if glob_match(pattern, subject) {
+            return true;
+        }
+    }
+
+    return false;
+}
+
+impl PathFilter {
+    // Whether a file at relative_path should be examined
+    pub fn allows(&self, relative_path: &Path) -> bool {
+        if self.includes.len() != 0 && !matches_any(&self.includes, relative_path) {
+            return false;
+        }
+
+        return !self.is_excluded(relative_path);
+    }
+
+    // Whether the path matches any of the exclude patterns. Excluded directories are not descended into
+    pub fn is_excluded(&self, relative_path: &Path) -> bool {
+        return matches_any(&self.excludes, relative_path);
+    }
+}
+
+// Recursively collects files under root that pass the filter, sorted by path.
+// Symbolic links are skipped unless follow_symlinks is set, in which case
+// every directory is visited only once so link cycles do not loop forever.
+// Entries that could not be read are put into errors and skipped
+pub fn walk_directory(
+    root: &Path,
+    follow_symlinks: bool,
+    filter: &PathFilter,
+    errors: &mut Vec<(PathBuf, std::io::Error)>,
+) -> Vec<WalkedFile> {
+    let mut files: Vec<WalkedFile> = Vec::new();
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    let mut pending: Vec<PathBuf> = vec![PathBuf::new()];
+
+    while let Some(relative_dir) = pending.pop() {
+        let dir: PathBuf = root.join(&relative_dir);
+
+        if follow_symlinks {
+            match std::fs::canonicalize(&dir) {
+                Ok(canonical) => {
+                    if !visited.insert(canonical) {
+                        continue;
+                    }
+                }
+                Err(error) => {
+                    errors.push((dir, error));
+                    continue;
+                }
+            }
+        }
+
+        let mut entry_names: Vec<std::ffi::OsString> = Vec::new();
+        match std::fs::read_dir(&dir) {
+            Ok(entries) => {
+                for entry in entries {
+                    match entry {
+                        Ok(entry) => entry_names.push(entry.file_name()),
+                        Err(error) => errors.push((dir.clone(), error)),
+                    }
+                }
+            }
+            Err(error) => {
+                errors.push((dir, error));
+                continue;
+            }
+        }
+
+        for entry_name in entry_names {
+            let relative_path: PathBuf = relative_dir.join(&entry_name);
+            let path: PathBuf = root.join(&relative_path);
+
+            let metadata: std::fs::Metadata;
+            let metadata_result: std::io::Result<std::fs::Metadata> = if follow_symlinks {std::fs::metadata(&path)} else {std::fs::symlink_metadata(&path)};
+            match metadata_result {
+                Ok(m) => {
+                    metadata = m;
+                }
+                Err(error) => {
+                    errors.push((path, error));
+                    continue;
+                }
+            }
+
+            if metadata.is_dir() {
+                if !filter.is_excluded(&relative_path) {
+                    pending.push(relative_path);
+                }
+            }
+            else if metadata.is_file() {
+                if filter.allows(&relative_path) {
+                    files.push(WalkedFile{
+                        path: path,
+                        relative_path: relative_path,
+                    });
+                }
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    return files;
+}